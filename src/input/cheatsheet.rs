@@ -0,0 +1,119 @@
+//! A readable summary of every binding, grouped by action enum, for help overlays and for
+//! exporting as markdown.
+use super::{diagnostics, Gesture, Switch};
+use std::fmt;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CheatSheet {
+    pub sections: Vec<CheatSheetSection>,
+}
+
+/// The bindings of a single action enum, e.g. `Pan`
+#[derive(Debug, Clone, PartialEq)]
+pub struct CheatSheetSection {
+    pub title: String,
+    pub entries: Vec<CheatSheetEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CheatSheetEntry {
+    /// The action variant, e.g. `Left`
+    pub action: String,
+    /// Every way of activating the action, e.g. `LControl + A (double tap)`
+    pub bindings: Vec<String>,
+}
+
+impl CheatSheet {
+    /// Build a cheat sheet from action names (`Enum::Variant`) and descriptions of their bindings
+    pub(super) fn new(bindings: impl IntoIterator<Item = (String, String)>) -> Self {
+        let mut sheet = CheatSheet::default();
+
+        for (name, binding) in bindings {
+            let (title, action) = match name.split_once("::") {
+                Some((title, action)) => (title.to_string(), action.to_string()),
+                None => (String::new(), name),
+            };
+
+            let section = match sheet.sections.iter().position(|s| s.title == title) {
+                Some(index) => &mut sheet.sections[index],
+                None => {
+                    sheet.sections.push(CheatSheetSection {
+                        title,
+                        entries: Vec::new(),
+                    });
+                    sheet.sections.last_mut().unwrap()
+                }
+            };
+
+            match section.entries.iter_mut().find(|e| e.action == action) {
+                Some(entry) => entry.bindings.push(binding),
+                None => section.entries.push(CheatSheetEntry {
+                    action,
+                    bindings: vec![binding],
+                }),
+            }
+        }
+
+        sheet.sections.sort_by(|a, b| a.title.cmp(&b.title));
+        for section in &mut sheet.sections {
+            section.entries.sort_by(|a, b| a.action.cmp(&b.action));
+            for entry in &mut section.entries {
+                entry.bindings.sort();
+            }
+        }
+
+        sheet
+    }
+
+    /// Render the cheat sheet as a markdown document with a table per action enum
+    pub fn to_markdown(&self) -> String {
+        let mut out = String::from("# Bindings\n");
+
+        for section in &self.sections {
+            out.push_str(&format!("\n## {}\n\n", section.title));
+            out.push_str("| Action | Bindings |\n| --- | --- |\n");
+
+            for entry in &section.entries {
+                let bindings: Vec<String> =
+                    entry.bindings.iter().map(|b| format!("`{}`", b)).collect();
+                out.push_str(&format!("| {} | {} |\n", entry.action, bindings.join(", ")));
+            }
+        }
+
+        out
+    }
+}
+
+/// Plain text rendering, one line per action
+impl fmt::Display for CheatSheet {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for section in &self.sections {
+            writeln!(f, "{}", section.title)?;
+
+            for entry in &section.entries {
+                writeln!(f, "  {:<16} {}", entry.action, entry.bindings.join(" or "))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Describe a combination and gesture as shown on the cheat sheet
+pub(super) fn describe_binding(switches: &[Switch], gesture: Gesture) -> String {
+    let combo = diagnostics::combo(switches);
+
+    match gesture {
+        Gesture::Press => combo,
+        Gesture::Tap { .. } => format!("{} (tap)", combo),
+        Gesture::DoubleTap { .. } => format!("{} (double tap)", combo),
+        Gesture::LongPress { threshold } => format!("{} (hold {}s)", combo, threshold),
+        Gesture::Repeat { .. } => format!("{} (repeats)", combo),
+    }
+}
+
+/// Describe a key sequence as shown on the cheat sheet, e.g. `Q, W`
+pub(super) fn describe_sequence(steps: &[Vec<Switch>]) -> String {
+    let steps: Vec<String> = steps.iter().map(|step| diagnostics::combo(step)).collect();
+    steps.join(", ")
+}
@@ -0,0 +1,273 @@
+//! Validation of the bindings held by a [`MappedInput`](super::MappedInput).
+//!
+//! Overlapping bindings are not errors as far as the binding graph is concerned, but they are
+//! easy to create by accident, particularly once bindings come from a keymap file. The checks
+//! here report:
+//!
+//! * combinations bound to more than one action,
+//! * combinations which can never fire while a larger combination with the same terminator in the
+//!   same context is held,
+//! * bindings which fire as a side effect of holding the layer of another binding.
+//!
+//! Duplicates and overlaps between bindings in different contexts are only reported as
+//! [`Severity::Info`], as splitting the same switches between contexts is what contexts are for.
+use super::{Gesture, Switch};
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Binding {
+    pub switches: Vec<Switch>,
+    pub action: String,
+    pub gesture: Gesture,
+    /// The context the action is assigned to, if any
+    pub context: Option<&'static str>,
+}
+
+impl fmt::Display for Binding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.gesture {
+            Gesture::Press => write!(f, "{} ({})", self.action, combo(&self.switches))?,
+            gesture => write!(
+                f,
+                "{} ({}, {:?})",
+                self.action,
+                combo(&self.switches),
+                gesture
+            )?,
+        }
+
+        match self.context {
+            Some(context) => write!(f, " in {}", context),
+            None => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Info,
+    Warning,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BindingDiagnostic {
+    /// The same switches are bound more than once. Bindings with identical terminators all
+    /// activate together, otherwise which one fires depends on the order the switches are pressed.
+    Duplicate { bindings: Vec<Binding> },
+    /// `binding` never activates while the layer of `by` is held, as the larger combination in
+    /// the same context wins.
+    Shadowed { binding: Binding, by: Binding },
+    /// `binding` activates as a side effect of holding the layer of `within`.
+    Overlapping { binding: Binding, within: Binding },
+}
+
+impl BindingDiagnostic {
+    /// Duplicates and overlaps are warnings when the bindings share a context. Across contexts
+    /// they are usually deliberate, e.g. a click which selects in one context and orders in
+    /// another, and whether both fire depends on which contexts are pushed and consume.
+    pub fn severity(&self) -> Severity {
+        let shared_context = match self {
+            BindingDiagnostic::Shadowed { .. } => false,
+            BindingDiagnostic::Duplicate { bindings } => bindings
+                .iter()
+                .enumerate()
+                .any(|(i, a)| bindings[i + 1..].iter().any(|b| a.context == b.context)),
+            BindingDiagnostic::Overlapping { binding, within } => binding.context == within.context,
+        };
+
+        if shared_context {
+            Severity::Warning
+        } else {
+            Severity::Info
+        }
+    }
+}
+
+impl fmt::Display for BindingDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BindingDiagnostic::Duplicate { bindings } => {
+                let actions: Vec<&str> = bindings.iter().map(|b| b.action.as_str()).collect();
+                write!(
+                    f,
+                    "{} are all bound to {}",
+                    actions.join(", "),
+                    combo(&bindings[0].switches)
+                )
+            }
+            BindingDiagnostic::Shadowed { binding, by } => {
+                write!(f, "{} is shadowed by {}", binding, by)
+            }
+            BindingDiagnostic::Overlapping { binding, within } => {
+                write!(f, "{} also activates when using {}", binding, within)
+            }
+        }
+    }
+}
+
+pub(super) fn combo(switches: &[Switch]) -> String {
+    let labels: Vec<String> = switches.iter().map(|s| format!("{}", s)).collect();
+    labels.join(" + ")
+}
+
+fn is_subset(a: &[Switch], b: &[Switch]) -> bool {
+    a.iter().all(|s| b.contains(s))
+}
+
+/// Check a set of bindings, given as the full switch combination (layer followed by the
+/// terminator) and the action name, against each other.
+pub(super) fn diagnose(bindings: &[Binding]) -> Vec<BindingDiagnostic> {
+    let mut diagnostics = Vec::new();
+
+    // group bindings by switch set, ignoring order. Different gestures on the same switches
+    // are deliberate, e.g. a click and a double click, so they are not duplicates.
+    let mut groups: Vec<Vec<&Binding>> = Vec::new();
+    for binding in bindings {
+        let group = groups.iter_mut().find(|group| {
+            let other = &group[0];
+            other.gesture == binding.gesture
+                && other.switches.len() == binding.switches.len()
+                && is_subset(&binding.switches, &other.switches)
+        });
+
+        match group {
+            Some(group) => group.push(binding),
+            None => groups.push(vec![binding]),
+        }
+    }
+
+    for group in groups.iter().filter(|group| group.len() > 1) {
+        diagnostics.push(BindingDiagnostic::Duplicate {
+            bindings: group.iter().map(|&b| b.clone()).collect(),
+        });
+    }
+
+    for binding in bindings {
+        let (terminator, layer) = binding.switches.split_last().unwrap();
+
+        for other in bindings {
+            let (other_terminator, other_layer) = other.switches.split_last().unwrap();
+
+            if other_layer.len() <= layer.len() || !is_subset(layer, other_layer) {
+                continue;
+            }
+
+            if terminator == other_terminator {
+                // the largest combination only wins within a context, whether a smaller one in
+                // another context also fires depends on which contexts are pushed and consume
+                if binding.context != other.context {
+                    continue;
+                }
+
+                diagnostics.push(BindingDiagnostic::Shadowed {
+                    binding: binding.clone(),
+                    by: other.clone(),
+                });
+            } else if other_layer.contains(terminator) {
+                diagnostics.push(BindingDiagnostic::Overlapping {
+                    binding: binding.clone(),
+                    within: other.clone(),
+                });
+            }
+        }
+    }
+
+    diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::Modifier;
+    use bevy::prelude::{KeyCode, MouseButton};
+
+    fn binding(switches: &[Switch], action: &str, context: Option<&'static str>) -> Binding {
+        Binding {
+            switches: switches.to_vec(),
+            action: action.to_string(),
+            gesture: Gesture::Press,
+            context,
+        }
+    }
+
+    #[test]
+    fn duplicates_across_contexts_are_info() {
+        let click = [Switch::from(MouseButton::Left)];
+        let bindings = [
+            binding(&click, "Select::Replace", Some("Selection")),
+            binding(&click, "Minimap::Focus", Some("Minimap")),
+        ];
+
+        let diagnostics = diagnose(&bindings);
+        assert_eq!(diagnostics.len(), 1);
+        assert!(matches!(
+            diagnostics[0],
+            BindingDiagnostic::Duplicate { .. }
+        ));
+        assert_eq!(diagnostics[0].severity(), Severity::Info);
+    }
+
+    #[test]
+    fn duplicates_within_a_context_are_warnings() {
+        let key = [Switch::from(KeyCode::X)];
+        let bindings = [
+            binding(&key, "Orders::Stop", Some("Commanding")),
+            binding(&key, "Orders::HoldPosition", Some("Commanding")),
+            binding(&key, "Pan::Left", Some("Camera")),
+        ];
+
+        let diagnostics = diagnose(&bindings);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity(), Severity::Warning);
+    }
+
+    #[test]
+    fn overlaps_follow_contexts() {
+        let shift = Switch::from(Modifier::Shift);
+        let click = Switch::from(MouseButton::Left);
+        let bindings = [
+            binding(&[shift], "Orders::Queue", Some("Commanding")),
+            binding(&[shift, click], "Select::Add", Some("Selection")),
+            binding(&[shift, click], "Orders::QueueMove", Some("Commanding")),
+        ];
+
+        let severities: Vec<(String, Severity)> = diagnose(&bindings)
+            .into_iter()
+            .filter_map(|diagnostic| match &diagnostic {
+                BindingDiagnostic::Overlapping { within, .. } => {
+                    Some((within.action.clone(), diagnostic.severity()))
+                }
+                _ => None,
+            })
+            .collect();
+
+        assert_eq!(
+            severities,
+            vec![
+                ("Select::Add".to_string(), Severity::Info),
+                ("Orders::QueueMove".to_string(), Severity::Warning),
+            ]
+        );
+    }
+
+    #[test]
+    fn shadowing_only_within_a_context() {
+        let click = Switch::from(MouseButton::Left);
+        let m = Switch::from(KeyCode::M);
+        let bindings = [
+            binding(&[click], "Select::Replace", Some("Selection")),
+            binding(&[m, click], "Orders::Move", Some("Commanding")),
+            binding(&[m, click], "Pan::Left", Some("Selection")),
+        ];
+
+        let shadowed: Vec<String> = diagnose(&bindings)
+            .into_iter()
+            .filter_map(|diagnostic| match diagnostic {
+                BindingDiagnostic::Shadowed { by, .. } => Some(by.action),
+                _ => None,
+            })
+            .collect();
+
+        assert_eq!(shadowed, vec!["Pan::Left".to_string()]);
+    }
+}
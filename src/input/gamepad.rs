@@ -0,0 +1,69 @@
+//! Gamepad buttons, axes and sticks as switches.
+//!
+//! Gamepad input is merged across all connected gamepads. Buttons are pressed once their analog
+//! value crosses the button threshold, while axes and sticks are pressed whenever they are
+//! deflected beyond their dead zone. The deflection beyond the dead zone is rescaled to the full
+//! `0..=1` range, and is available from [`MappedInput::axis`](super::MappedInput::axis) and
+//! [`MappedInput::stick`](super::MappedInput::stick) in the same way mouse deltas are available
+//! from [`MappedInput::motion`](super::MappedInput::motion).
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Hash, PartialEq, Eq, Copy, Clone, Serialize, Deserialize)]
+pub enum Stick {
+    Left,
+    Right,
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct DeadZones {
+    /// Radial dead zone applied to the magnitude of each stick
+    pub stick: f32,
+    /// Dead zone applied to individual axes
+    pub axis: f32,
+    /// Analog value at which a button counts as pressed
+    pub button: f32,
+}
+
+impl Default for DeadZones {
+    fn default() -> Self {
+        DeadZones {
+            stick: 0.15,
+            axis: 0.1,
+            button: 0.5,
+        }
+    }
+}
+
+/// The stick an axis belongs to, and whether it is the x axis of that stick
+pub(super) fn stick_axis(axis: GamepadAxisType) -> Option<(Stick, bool)> {
+    match axis {
+        GamepadAxisType::LeftStickX => Some((Stick::Left, true)),
+        GamepadAxisType::LeftStickY => Some((Stick::Left, false)),
+        GamepadAxisType::RightStickX => Some((Stick::Right, true)),
+        GamepadAxisType::RightStickY => Some((Stick::Right, false)),
+        _ => None,
+    }
+}
+
+/// Remove the dead zone from a single axis value, rescaling the remainder to `-1..=1`
+pub(super) fn apply_dead_zone(value: f32, dead_zone: f32) -> f32 {
+    let magnitude = value.abs();
+
+    if magnitude <= dead_zone {
+        0.0
+    } else {
+        value.signum() * ((magnitude - dead_zone) / (1.0 - dead_zone)).min(1.0)
+    }
+}
+
+/// Remove a radial dead zone from a stick, keeping its direction
+pub(super) fn apply_radial_dead_zone(value: Vec2, dead_zone: f32) -> Vec2 {
+    let magnitude = value.length();
+
+    if magnitude <= dead_zone {
+        Vec2::ZERO
+    } else {
+        value / magnitude * apply_dead_zone(magnitude, dead_zone)
+    }
+}
@@ -0,0 +1,52 @@
+//! Timed gestures modify when a binding activates its action.
+//!
+//! By default an action is active for as long as its combination is held. A binding made with
+//! [`MappedInput::bind_gesture`](super::MappedInput::bind_gesture) can instead require the
+//! combination to be tapped, double tapped or held for some time, or can repeatedly re-trigger
+//! `just_activated` while it is held. All times are in seconds.
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum Gesture {
+    /// Active while the combination is held
+    Press,
+    /// Active for a single frame when the combination is released within `max` of being pressed
+    Tap { max: f32 },
+    /// Activates when the combination is pressed a second time within `window` of the first
+    /// press, and stays active while held
+    DoubleTap { window: f32 },
+    /// Activates once the combination has been held for `threshold`, and stays active while held
+    LongPress { threshold: f32 },
+    /// Activates when pressed, then after `delay` reports `just_activated` again `rate` times a
+    /// second for as long as the combination is held
+    Repeat { delay: f32, rate: f32 },
+}
+
+impl Default for Gesture {
+    fn default() -> Self {
+        Gesture::Press
+    }
+}
+
+/// Timing state of a gesture whose combination is currently held
+#[derive(Debug, Clone, Copy)]
+pub(super) struct HeldGesture {
+    pub pressed_at: f64,
+    /// When the gesture next needs attention from `MappedInput::update`
+    pub next: f64,
+}
+
+impl HeldGesture {
+    pub fn new(gesture: Gesture, now: f64) -> Self {
+        let next = match gesture {
+            Gesture::LongPress { threshold } => now + threshold as f64,
+            Gesture::Repeat { delay, .. } => now + delay as f64,
+            _ => f64::INFINITY,
+        };
+
+        HeldGesture {
+            pressed_at: now,
+            next,
+        }
+    }
+}
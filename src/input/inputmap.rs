@@ -35,13 +35,62 @@
 //! Layer edges are added from the LAlt and LCtrl nodes to the LCtrl + LAlt node. An Action edge is created
 //! from the A node to the LCtrl + LAlt node, with the DodgeLefter action.
 //!
+//! Bindings can be removed again with [`MappedInput::unbind`] or replaced wholesale with
+//! [`MappedInput::rebind`]. Removing a binding also removes any compound layer node which no
+//! longer has an action bound to it, along with its layer edges.
+//!
+//! # Contexts
+//! Action types can be assigned to named contexts with [`MappedInput::assign_context`]. Actions
+//! in a context only activate while that context is on the context stack, and the topmost
+//! context which has an action bound to a switch wins. A context pushed with `consume` set stops
+//! a switch it uses from also activating actions in the contexts beneath it.
+//!
+//! ```
+//!  inputs.assign_context::<MenuActions>("Menu");
+//!  inputs.push_context("Menu", true);
+//! ```
+//!
+//! # Gestures
+//! Bindings made with [`MappedInput::bind_gesture`] activate according to a timed [`Gesture`],
+//! such as a double tap or a long press, rather than for as long as the combination is held.
+//! Gesture timing is driven by the time passed to `update` at the start of every frame.
+//!
+//! ```
+//!  inputs.bind_gesture([KeyCode::S], MyActions::Scuttle, Gesture::LongPress { threshold: 0.5 });
+//! ```
+//!
+//! # Sequences
+//! [`MappedInput::bind_sequence`] binds an action to combinations pressed one after another.
+//! Started sequences can be listed with [`MappedInput::pending_sequences`], e.g. to show which
+//! key is expected next.
+//!
+//! ```
+//!  inputs.bind_sequence([[KeyCode::Q], [KeyCode::W]], MyActions::BuildFrigate, 1.0);
+//! ```
+//!
+//! [`MappedInput::validate`] reports bindings which overlap each other.
+//!
+//! Bindings can be exported to, and rebuilt from, a [`Keymap`] so that they can be edited in a
+//! keymap file. See the [`keymap`](super::keymap) module for the file format.
+//!
 //! # TODO
 //! Finish documentation
-use super::Switch;
-use bevy::prelude::Vec2;
+use super::{
+    cheatsheet::{self, CheatSheet},
+    diagnostics::{self, Binding, BindingDiagnostic},
+    gamepad::{self, DeadZones, Stick},
+    gesture::{Gesture, HeldGesture},
+    keymap::{Keymap, KeymapEntry, KeymapError},
+    modifier::Modifier,
+    recording::{InputEvent, RecordedFrame},
+    sequence::{Sequence, SequenceId, SequenceProgress},
+    Switch,
+};
+use bevy::prelude::{GamepadAxisType, GamepadButtonType, KeyCode, Vec2};
 use num_traits::ToPrimitive;
 use petgraph::{
-    graph::{DiGraph, EdgeIndex, NodeIndex},
+    stable_graph::{EdgeIndex, NodeIndex, StableDiGraph},
+    visit::{EdgeRef, IntoEdgeReferences},
     Direction,
 };
 use std::{
@@ -52,9 +101,11 @@ use std::{
 
 use log::debug;
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, PartialEq)]
 enum Edge {
-    Action(ActionId),
+    Action(ActionId, Gesture),
+    /// A step of a key sequence, by sequence and step index
+    Step(SequenceId, usize),
     Layer,
 }
 
@@ -63,14 +114,29 @@ struct Node {
     //label: String,
     active: u8,
     threshold: u8,
+    switches: Vec<Switch>,
 }
 
-type ActionId = (TypeId, u16);
+pub type ActionId = (TypeId, u16);
+
+/// A named group of action types which can be enabled by pushing it onto the context stack
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InputContext {
+    pub name: &'static str,
+    pub consume: bool,
+}
 
 pub trait Action: 'static + ToPrimitive + Send + Sync + std::fmt::Debug {
     fn to_id(&self) -> ActionId {
         (TypeId::of::<Self>(), self.to_u16().unwrap())
     }
+
+    /// The name used to refer to this action in keymap files, e.g. `Pan::Left`
+    fn name(&self) -> String {
+        let type_name = std::any::type_name::<Self>();
+        let short_name = type_name.rsplit("::").next().unwrap_or(type_name);
+        format!("{}::{:?}", short_name, self)
+    }
 }
 
 impl<T: 'static + ToPrimitive + Send + Sync + std::fmt::Debug> Action for T {}
@@ -80,64 +146,478 @@ pub struct MappedInput {
     //boxed_types: HashMap<ActionId, Box<dyn Action>>,
     edge_labels: HashMap<EdgeIndex, String>,
     node_labels: HashMap<NodeIndex, String>,
-    bindings: DiGraph<Node, Edge>,
+    action_names: HashMap<ActionId, String>,
+    named_actions: HashMap<String, ActionId>,
+    bindings: StableDiGraph<Node, Edge>,
     nodes: HashMap<Vec<Switch>, NodeIndex>,
+    contexts: HashMap<TypeId, &'static str>,
+    context_stack: Vec<InputContext>,
     active: HashSet<ActionId>,
     just_activated: HashSet<ActionId>,
     just_deactivated: HashSet<ActionId>,
+    /// Actions deactivated this frame without their switches being released, e.g. because their
+    /// context was removed or their binding was unbound
+    cancelled: HashSet<ActionId>,
+    /// Set while [`MappedInput::release_all`] releases every switch, which cancels the actions
+    /// it deactivates rather than completing them
+    cancelling: bool,
     mouse_motion: Vec2,
     mouse_scroll: f32,
+    /// Cursor position in window coordinates, if the cursor is inside the window
+    cursor: Option<Vec2>,
+    /// Physical modifier keys currently held, which hold their logical modifier switch
+    modifier_keys: HashSet<KeyCode>,
+    dead_zones: DeadZones,
+    gamepad_buttons: HashMap<GamepadButtonType, f32>,
+    gamepad_axes: HashMap<GamepadAxisType, f32>,
+    /// The terminating switch which last activated each action, used to look up analog values
+    action_sources: HashMap<ActionId, Switch>,
+    /// Time of the current frame, in seconds since startup
+    now: f64,
+    /// Time between the previous frame and the current one, in seconds
+    delta: f32,
+    held_gestures: HashMap<EdgeIndex, HeldGesture>,
+    last_presses: HashMap<EdgeIndex, f64>,
+    /// Actions activated by a tap, which are deactivated again on the next update
+    pulses: Vec<ActionId>,
+    sequences: HashMap<SequenceId, Sequence>,
+    next_sequence: SequenceId,
+    /// Sequences advanced by the switch currently being pressed
+    advanced_sequences: HashSet<SequenceId>,
 }
 
 impl MappedInput {
     /// Bind an action to a key binding
 
     pub fn bind<I, S>(&mut self, keys: I, action: impl Action)
+    where
+        S: Into<Switch>,
+        I: IntoIterator<Item = S>,
+    {
+        self.bind_gesture(keys, action, Gesture::Press);
+    }
+
+    /// Bind an action to a key binding, activating it according to a timed gesture
+    pub fn bind_gesture<I, S>(&mut self, keys: I, action: impl Action, gesture: Gesture)
+    where
+        S: Into<Switch>,
+        I: IntoIterator<Item = S>,
+    {
+        let keys = keys.into_iter().map(|i| i.into()).collect::<Vec<Switch>>();
+
+        self.register(&action);
+        self.bind_switches(&keys, action.to_id(), gesture);
+    }
+
+    /// Bind an action to a sequence of key combinations, which must be pressed in order with no
+    /// more than `timeout` seconds between each
+    pub fn bind_sequence<I, K, S>(&mut self, steps: I, action: impl Action, timeout: f32)
+    where
+        S: Into<Switch>,
+        K: IntoIterator<Item = S>,
+        I: IntoIterator<Item = K>,
+    {
+        let steps: Vec<Vec<Switch>> = steps
+            .into_iter()
+            .map(|keys| keys.into_iter().map(|i| i.into()).collect())
+            .collect();
+
+        assert!(!steps.is_empty(), "Received empty sequence");
+
+        self.register(&action);
+
+        let id = self.next_sequence;
+        self.next_sequence += 1;
+
+        self.sequences.insert(
+            id,
+            Sequence {
+                action: action.to_id(),
+                steps,
+                timeout,
+                progress: 0,
+                deadline: 0.0,
+            },
+        );
+
+        self.add_sequence_edges(id);
+    }
+
+    fn add_sequence_edges(&mut self, id: SequenceId) {
+        let steps = self.sequences[&id].steps.clone();
+
+        for (index, keys) in steps.iter().enumerate() {
+            let (&terminator, layer) = keys.split_last().expect("Received empty sequence step");
+
+            let terminator_node = self.get_or_create_node(&[terminator]);
+            let layer_node = self.get_or_create_node(layer);
+            self.add_edge(terminator_node, layer_node, Edge::Step(id, index));
+
+            if layer.len() > 1 {
+                for &switch in layer {
+                    let switch_node = self.get_or_create_node(&[switch]);
+                    self.add_edge(switch_node, layer_node, Edge::Layer);
+                }
+            }
+        }
+    }
+
+    /// Remove every sequence bound to an action, returning whether there were any
+    pub fn unbind_sequences(&mut self, action: impl Action) -> bool {
+        let action = action.to_id();
+
+        let ids: Vec<SequenceId> = self
+            .sequences
+            .iter()
+            .filter(|(_, sequence)| sequence.action == action)
+            .map(|(&id, _)| id)
+            .collect();
+
+        let edges: Vec<(EdgeIndex, NodeIndex, NodeIndex)> = self
+            .bindings
+            .edge_references()
+            .filter(|edge| matches!(*edge.weight(), Edge::Step(id, _) if ids.contains(&id)))
+            .map(|edge| (edge.id(), edge.source(), edge.target()))
+            .collect();
+
+        for &(edge, _, _) in &edges {
+            self.bindings.remove_edge(edge);
+            self.edge_labels.remove(&edge);
+        }
+
+        for (_, terminator_node, layer_node) in edges {
+            if self.bindings.contains_node(layer_node) {
+                self.remove_if_orphaned(layer_node);
+            }
+            if self.bindings.contains_node(terminator_node) {
+                self.remove_if_orphaned(terminator_node);
+            }
+        }
+
+        for id in &ids {
+            self.sequences.remove(id);
+        }
+
+        !ids.is_empty()
+    }
+
+    /// Sequences which have been started but not completed
+    pub fn pending_sequences(&self) -> Vec<SequenceProgress<'_>> {
+        self.sequences
+            .values()
+            .filter(|sequence| sequence.progress > 0)
+            .map(|sequence| SequenceProgress {
+                action: sequence.action,
+                matched: &sequence.steps[..sequence.progress],
+                remaining: &sequence.steps[sequence.progress..],
+                time_left: (sequence.deadline - self.now).max(0.0) as f32,
+            })
+            .collect()
+    }
+
+    /// Whether a sequence bound to the action has been started but not completed
+    pub fn sequence_pending(&self, action: impl Action) -> bool {
+        let action = action.to_id();
+
+        self.sequences
+            .values()
+            .any(|sequence| sequence.action == action && sequence.progress > 0)
+    }
+
+    /// Make an action known by name, so that it can be referred to in keymap files
+    fn register(&mut self, action: &dyn Action) {
+        let id = action.to_id();
+
+        if !self.action_names.contains_key(&id) {
+            let name = action.name();
+            self.named_actions.insert(name.clone(), id);
+            self.action_names.insert(id, name);
+        }
+    }
+
+    /// Remove the binding of a key combination to an action.
+    ///
+    /// Returns false if the combination was not bound to the action.
+    pub fn unbind<I, S>(&mut self, keys: I, action: impl Action) -> bool
     where
         S: Into<Switch>,
         I: IntoIterator<Item = S>,
     {
         let keys = keys.into_iter().map(|i| i.into()).collect::<Vec<Switch>>();
 
+        self.unbind_switches(&keys, action.to_id())
+    }
+
+    /// Remove every binding of an action, returning the key combinations it was bound to
+    pub fn unbind_all(&mut self, action: impl Action) -> Vec<Vec<Switch>> {
+        let action = action.to_id();
+        let removed = self.bindings_for_id(action);
+
+        for keys in &removed {
+            self.unbind_switches(keys, action);
+        }
+
+        removed
+    }
+
+    /// Replace every binding of an action with a single new key combination
+    pub fn rebind<I, S>(&mut self, keys: I, action: impl Action)
+    where
+        S: Into<Switch>,
+        I: IntoIterator<Item = S>,
+    {
+        self.register(&action);
+        let id = action.to_id();
+
+        for old in self.bindings_for_id(id) {
+            self.unbind_switches(&old, id);
+        }
+
+        let keys = keys.into_iter().map(|i| i.into()).collect::<Vec<Switch>>();
+        self.bind_switches(&keys, id, Gesture::Press);
+    }
+
+    /// Every key combination the action is currently bound to
+    pub fn bindings_for(&self, action: impl Action) -> Vec<Vec<Switch>> {
+        self.bindings_for_id(action.to_id())
+    }
+
+    fn bindings_for_id(&self, action: ActionId) -> Vec<Vec<Switch>> {
+        self.all_bindings()
+            .into_iter()
+            .filter(|&(_, a, _)| a == action)
+            .map(|(keys, _, _)| keys)
+            .collect()
+    }
+
+    /// The actions bound to exactly this key combination. The switches in the layer may be given
+    /// in any order, but the last switch must be the terminator.
+    pub fn action_for<I, S>(&self, keys: I) -> Vec<ActionId>
+    where
+        S: Into<Switch>,
+        I: IntoIterator<Item = S>,
+    {
+        let keys = keys.into_iter().map(|i| i.into()).collect::<Vec<Switch>>();
+
+        match self.find_binding_nodes(&keys) {
+            Some((terminator_node, layer_node)) => self
+                .bindings
+                .edges(terminator_node)
+                .filter(|edge| edge.target() == layer_node)
+                .filter_map(|edge| match *edge.weight() {
+                    Edge::Action(action, _) => Some(action),
+                    Edge::Step(..) | Edge::Layer => None,
+                })
+                .collect(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Check the current bindings for duplicates and shadowing
+    pub fn validate(&self) -> Vec<BindingDiagnostic> {
+        let bindings: Vec<Binding> = self
+            .all_bindings()
+            .into_iter()
+            .map(|(switches, action, gesture)| Binding {
+                switches,
+                action: self.action_name(action),
+                gesture,
+                context: self.contexts.get(&action.0).copied(),
+            })
+            .collect();
+
+        diagnostics::diagnose(&bindings)
+    }
+
+    /// The name of an action as used in keymap files
+    pub fn action_name(&self, action: ActionId) -> String {
+        self.action_names
+            .get(&action)
+            .cloned()
+            .unwrap_or_else(|| format!("{:?}", action))
+    }
+
+    fn bind_switches(&mut self, keys: &[Switch], action: ActionId, gesture: Gesture) {
         let (&terminator, layer) = keys.split_last().expect("Received empty binding");
 
-        let terminator_node = self.get_or_create_node(&[terminator.into()]);
+        let terminator_node = self.get_or_create_node(&[terminator]);
 
-        let layer_node = self.get_or_create_node(&layer);
-        self.add_edge(terminator_node, layer_node, Some(&action));
+        let layer_node = self.get_or_create_node(layer);
+        self.add_edge(terminator_node, layer_node, Edge::Action(action, gesture));
 
         if layer.len() > 1 {
             for &switch in layer {
                 let switch_node = self.get_or_create_node(&[switch]);
-                self.add_edge(switch_node, layer_node, None);
+                self.add_edge(switch_node, layer_node, Edge::Layer);
+            }
+        }
+    }
+
+    fn unbind_switches(&mut self, keys: &[Switch], action: ActionId) -> bool {
+        let (terminator_node, layer_node) = match self.find_binding_nodes(keys) {
+            Some(nodes) => nodes,
+            None => return false,
+        };
+
+        let edge = self
+            .bindings
+            .edges(terminator_node)
+            .find(|edge| {
+                edge.target() == layer_node
+                    && matches!(*edge.weight(), Edge::Action(a, _) if a == action)
+            })
+            .map(|edge| edge.id());
+
+        let edge = match edge {
+            Some(edge) => edge,
+            None => return false,
+        };
+
+        debug!("Unbinding {}", self.edge_labels[&edge]);
+
+        self.bindings.remove_edge(edge);
+        self.edge_labels.remove(&edge);
+        self.held_gestures.remove(&edge);
+        self.last_presses.remove(&edge);
+
+        if self.active.remove(&action) {
+            self.just_deactivated.insert(action);
+            self.cancelled.insert(action);
+        }
+
+        self.remove_if_orphaned(layer_node);
+        self.remove_if_orphaned(terminator_node);
+
+        true
+    }
+
+    /// Remove a node which no longer takes part in any binding.
+    ///
+    /// Compound layer nodes are orphaned as soon as no action is bound through them, and take
+    /// their layer edges with them. Single switch nodes (and the root) are only removed once
+    /// they have no edges at all, and are not currently pressed.
+    fn remove_if_orphaned(&mut self, index: NodeIndex) {
+        let has_actions = self
+            .bindings
+            .edges_directed(index, Direction::Incoming)
+            .any(|edge| *edge.weight() != Edge::Layer);
+
+        if has_actions {
+            return;
+        }
+
+        let node = &self.bindings[index];
+        let is_layer = node.switches.len() > 1;
+
+        if !is_layer
+            && (node.active > 0 || self.bindings.neighbors_undirected(index).next().is_some())
+        {
+            return;
+        }
+
+        let switches = node.switches.clone();
+
+        let layer_edges: Vec<EdgeIndex> = self
+            .bindings
+            .edges_directed(index, Direction::Incoming)
+            .map(|edge| edge.id())
+            .collect();
+
+        for edge in layer_edges {
+            self.edge_labels.remove(&edge);
+        }
+
+        debug!("Removing orphaned node {}", self.node_labels[&index]);
+
+        self.bindings.remove_node(index);
+        self.nodes.remove(&switches);
+        self.node_labels.remove(&index);
+
+        if is_layer {
+            for switch in switches {
+                if let Some(switch_node) = self.find_node(&[switch]) {
+                    self.remove_if_orphaned(switch_node);
+                }
             }
         }
     }
 
     /// Add an edge _edge_ from a to b, and update edge labels
-    fn add_edge(&mut self, a: NodeIndex, b: NodeIndex, action: Option<&dyn Action>) {
-        let edge = match &action {
-            Some(action) => Edge::Action(action.to_id()),
-            None => Edge::Layer,
+    fn add_edge(&mut self, a: NodeIndex, b: NodeIndex, edge: Edge) {
+        // several actions may be bound to the same combination, so an existing edge is only
+        // reused if it carries the same action and gesture
+        let existing = self
+            .bindings
+            .edges(a)
+            .find(|e| e.target() == b && *e.weight() == edge)
+            .map(|e| e.id());
+
+        let edge_idx = match existing {
+            Some(edge_idx) => edge_idx,
+            None => self.bindings.add_edge(a, b, edge),
         };
-        let edge_idx = self.bindings.update_edge(a, b, edge);
 
-        let label = match &action {
-            Some(action) => format!("{:?}", action),
-            None => "Layer".to_string(),
+        let label = match edge {
+            Edge::Action(action, Gesture::Press) => self.action_name(action),
+            Edge::Action(action, gesture) => {
+                format!("{} ({:?})", self.action_name(action), gesture)
+            }
+            Edge::Step(id, step) => {
+                let sequence = &self.sequences[&id];
+                format!(
+                    "{} [{}/{}]",
+                    self.action_name(sequence.action),
+                    step + 1,
+                    sequence.steps.len()
+                )
+            }
+            Edge::Layer => "Layer".to_string(),
         };
 
         self.edge_labels.insert(edge_idx, label);
     }
 
-    fn get_or_create_node(&mut self, keys: &[Switch]) -> NodeIndex {
+    /// Find the node for a set of switches, regardless of the order they are given in
+    fn find_node(&self, keys: &[Switch]) -> Option<NodeIndex> {
         if let Some(&index) = self.nodes.get(keys) {
+            return Some(index);
+        }
+
+        self.nodes
+            .iter()
+            .find(|(switches, _)| {
+                switches.len() == keys.len() && keys.iter().all(|key| switches.contains(key))
+            })
+            .map(|(_, &index)| index)
+    }
+
+    /// Find the terminator and layer nodes of a key combination
+    fn find_binding_nodes(&self, keys: &[Switch]) -> Option<(NodeIndex, NodeIndex)> {
+        let (&terminator, layer) = keys.split_last()?;
+
+        Some((self.find_node(&[terminator])?, self.find_node(layer)?))
+    }
+
+    fn get_or_create_node(&mut self, keys: &[Switch]) -> NodeIndex {
+        if let Some(index) = self.find_node(keys) {
             return index;
         }
 
+        // a layer bound while some of its switches are held starts out counting them, as their
+        // layer edges will be decremented when they are released
+        let active = if keys.len() > 1 {
+            keys.iter()
+                .filter_map(|&switch| self.nodes.get(&[switch].to_vec()))
+                .filter(|&&index| self.bindings[index].active > 0)
+                .count() as u8
+        } else {
+            0
+        };
+
         let index = self.bindings.add_node(Node {
-            active: 0,
+            active,
             threshold: keys.len() as u8,
+            switches: keys.to_owned(),
         });
 
         self.nodes.insert(keys.to_owned(), index);
@@ -155,13 +635,71 @@ impl MappedInput {
         labels.join(" + ")
     }
 
-    pub(crate) fn update(&mut self) {
+    /// Begin a new frame at time `now`, in seconds since startup
+    pub(crate) fn update(&mut self, now: f64) {
+        self.delta = (now - self.now).max(0.0) as f32;
+        self.now = now;
         self.just_activated.clear();
         self.just_deactivated.clear();
+        self.cancelled.clear();
         self.mouse_motion = Vec2::ZERO;
         self.mouse_scroll = 0f32;
         self.deactivate(Switch::MouseMotion);
         self.deactivate(Switch::MouseScroll);
+        self.update_gestures();
+        self.update_sequences();
+    }
+
+    /// Abandon sequences whose next step was not pressed in time
+    fn update_sequences(&mut self) {
+        let now = self.now;
+
+        for sequence in self.sequences.values_mut() {
+            if sequence.progress > 0 && now > sequence.deadline {
+                sequence.progress = 0;
+            }
+        }
+    }
+
+    /// End tap pulses from the previous frame, and fire long presses and repeats which are due
+    fn update_gestures(&mut self) {
+        for action in std::mem::take(&mut self.pulses) {
+            if self.active.remove(&action) {
+                self.just_deactivated.insert(action);
+            }
+        }
+
+        let now = self.now;
+        let due: Vec<EdgeIndex> = self
+            .held_gestures
+            .iter()
+            .filter(|(_, held)| held.next <= now)
+            .map(|(&edge, _)| edge)
+            .collect();
+
+        for edge in due {
+            let (action, gesture) = match self.bindings.edge_weight(edge) {
+                Some(&Edge::Action(action, gesture)) => (action, gesture),
+                _ => continue,
+            };
+
+            let held = self.held_gestures.get_mut(&edge).unwrap();
+
+            match gesture {
+                Gesture::LongPress { .. } => {
+                    held.next = f64::INFINITY;
+                    debug!("Long press: {}", self.edge_labels[&edge]);
+                    if self.active.insert(action) {
+                        self.just_activated.insert(action);
+                    }
+                }
+                Gesture::Repeat { rate, .. } => {
+                    held.next = (held.next + 1.0 / rate.max(f32::EPSILON) as f64).max(now);
+                    self.just_activated.insert(action);
+                }
+                _ => held.next = f64::INFINITY,
+            }
+        }
     }
 
     /// Deactivate a bound action
@@ -186,9 +724,36 @@ impl MappedInput {
                         *active -= 1;
                         debug!("Decrementing node {:?}:", self.node_labels[&node]);
                     }
-                    (Edge::Action(action), _) => {
-                        if self.active.remove(&action) {
+                    (Edge::Action(action, gesture), _) => {
+                        let held = self.held_gestures.remove(&edge);
+
+                        if let (Gesture::Tap { max }, Some(held)) = (gesture, held) {
+                            // taps only activate on release, and only for a single frame
+                            if !self.cancelling
+                                && self.now - held.pressed_at <= max as f64
+                                && self.active.insert(action)
+                            {
+                                self.just_activated.insert(action);
+                                self.pulses.push(action);
+                                debug!("Tapped {}", self.edge_labels[&edge]);
+                            }
+                        } else if self.active.remove(&action) {
                             self.just_deactivated.insert(action);
+                            if self.cancelling {
+                                self.cancelled.insert(action);
+                            }
+                            debug!("Deactivating {}", self.edge_labels[&edge]);
+                        }
+                    }
+                    (Edge::Step(id, step), _) => {
+                        // sequence actions end when the final step is released
+                        let sequence = &self.sequences[&id];
+                        if step + 1 == sequence.steps.len() && self.active.remove(&sequence.action)
+                        {
+                            self.just_deactivated.insert(sequence.action);
+                            if self.cancelling {
+                                self.cancelled.insert(sequence.action);
+                            }
                             debug!("Deactivating {}", self.edge_labels[&edge]);
                         }
                     }
@@ -216,44 +781,97 @@ impl MappedInput {
                 .neighbors_directed(index, Direction::Outgoing)
                 .detach();
 
-            let mut highest_threshold = -1i32;
-            let mut target_action = None;
+            // (context level, threshold, layer) of every satisfied binding
+            let mut candidates: Vec<(i32, u8, NodeIndex)> = Vec::new();
 
             while let Some((edge, node)) = neighbours.next(&self.bindings) {
+                let level = self.edge_level(self.bindings[edge]);
+
                 match (self.bindings[edge], &mut self.bindings[node]) {
                     (Edge::Layer, Node { active, .. }) => {
                         *active += 1;
                         debug!("Incrementing node {}:", self.node_labels[&node]);
                     }
                     (
-                        Edge::Action(action),
+                        _,
                         Node {
                             ref active,
                             ref threshold,
                             ..
                         },
                     ) if active >= threshold => {
-                        debug!(
-                            "Threshold: {:?} Active: {:?}, Max: {:?}",
-                            threshold, active, highest_threshold
-                        );
-                        // # TODO: Eventually we want our edges to be presorted
-                        // in order of threshold, so that the highest threshold
-                        // comes first, and then we can just break on the first result
-                        //if self.active.insert(action) {
-                        //    self.just_activated.insert(action);
-                        //    break;
-                        //}
-
-                        if (*threshold as i32) > highest_threshold {
-                            target_action = Some((action, edge));
-                            highest_threshold = *threshold as i32;
+                        debug!("Threshold: {:?} Active: {:?}", threshold, active);
+
+                        if let Some(level) = level {
+                            candidates.push((level, *threshold, node));
                         }
                     }
-                    _ => {}
                 }
             }
-            if let Some((action, edge)) = target_action {
+
+            // # TODO: Eventually we want our edges to be presorted
+            // in order of threshold, so that the highest threshold
+            // comes first, and then we can just break on the first result
+            candidates.sort_unstable_by(|a, b| b.cmp(a));
+            // within each context only the combination with the highest threshold wins
+            candidates.dedup_by_key(|&mut (level, _, _)| level);
+
+            for (level, _, layer) in candidates {
+                self.activate_combination(index, layer, level);
+
+                if self.context_consumes(level) {
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Activate every action of a context level bound to the combination of terminator and layer
+    fn activate_combination(&mut self, terminator: NodeIndex, layer: NodeIndex, level: i32) {
+        let targets: Vec<(Edge, EdgeIndex)> = self
+            .bindings
+            .edges(terminator)
+            .filter(|edge| edge.target() == layer)
+            .map(|edge| (*edge.weight(), edge.id()))
+            .filter(|&(weight, _)| self.edge_level(weight) == Some(level))
+            .collect();
+
+        let source = self.bindings[terminator].switches[0];
+        let mut steps: HashMap<SequenceId, Vec<usize>> = HashMap::new();
+
+        for (weight, edge) in targets {
+            let (action, gesture) = match weight {
+                Edge::Action(action, gesture) => (action, gesture),
+                Edge::Step(id, step) => {
+                    steps.entry(id).or_default().push(step);
+                    continue;
+                }
+                Edge::Layer => continue,
+            };
+
+            self.action_sources.insert(action, source);
+
+            let activate_now = match gesture {
+                Gesture::Press | Gesture::Repeat { .. } => true,
+                Gesture::Tap { .. } | Gesture::LongPress { .. } => false,
+                Gesture::DoubleTap { window } => match self.last_presses.insert(edge, self.now) {
+                    Some(last) if self.now - last <= window as f64 => {
+                        // a third press starts a new double tap
+                        self.last_presses.remove(&edge);
+                        true
+                    }
+                    _ => false,
+                },
+            };
+
+            if let Gesture::Tap { .. } | Gesture::LongPress { .. } | Gesture::Repeat { .. } =
+                gesture
+            {
+                self.held_gestures
+                    .insert(edge, HeldGesture::new(gesture, self.now));
+            }
+
+            if activate_now {
                 debug!(
                     "Activating: {}",
                     self.edge_labels[&edge] //to_debug_edge(&Edge::Action(action), &self)
@@ -263,10 +881,168 @@ impl MappedInput {
                 }
             }
         }
+
+        for (id, steps) in steps {
+            self.advance_sequence(id, &steps, source);
+        }
+    }
+
+    /// Advance a sequence given the steps matched by the current press. The same combination may
+    /// appear as several steps of one sequence, so all of them are considered at once.
+    fn advance_sequence(&mut self, id: SequenceId, steps: &[usize], source: Switch) {
+        let now = self.now;
+        let sequence = self.sequences.get_mut(&id).unwrap();
+
+        self.advanced_sequences.insert(id);
+
+        if sequence.progress > 0 && now <= sequence.deadline && steps.contains(&sequence.progress) {
+            sequence.progress += 1;
+        } else if steps.contains(&0) {
+            sequence.progress = 1;
+        } else {
+            sequence.progress = 0;
+            return;
+        }
+
+        sequence.deadline = now + sequence.timeout as f64;
+
+        if sequence.progress == sequence.steps.len() {
+            sequence.progress = 0;
+            let action = sequence.action;
+
+            debug!("Completed sequence for {}", self.action_name(action));
+            self.action_sources.insert(action, source);
+            if self.active.insert(action) {
+                self.just_activated.insert(action);
+            }
+        }
+    }
+
+    /// Abandon any started sequence which the pressed switch did not advance, unless the switch
+    /// belongs to the layer of the next step, e.g. a modifier pressed ahead of the next key
+    fn abandon_sequences(&mut self, pressed: Switch) {
+        for (id, sequence) in self.sequences.iter_mut() {
+            if sequence.progress == 0 || self.advanced_sequences.contains(id) {
+                continue;
+            }
+
+            let (_, layer) = sequence.steps[sequence.progress].split_last().unwrap();
+            let modifier = match pressed {
+                Switch::Key(key) => Modifier::from_key(key).map(Switch::Modifier),
+                _ => None,
+            };
+
+            if !layer.contains(&pressed) && !modifier.map_or(false, |m| layer.contains(&m)) {
+                sequence.progress = 0;
+            }
+        }
+    }
+
+    /// The context level of the action carried by an edge
+    fn edge_level(&self, edge: Edge) -> Option<i32> {
+        match edge {
+            Edge::Action(action, _) => self.context_level(action),
+            Edge::Step(id, _) => self.context_level(self.sequences[&id].action),
+            Edge::Layer => None,
+        }
+    }
+
+    /// Assign every action of type `A` to a named context. The actions will only activate while
+    /// the context is on the context stack. Actions which are not assigned to a context are
+    /// always enabled.
+    pub fn assign_context<A: Action>(&mut self, context: &'static str) {
+        self.contexts.insert(TypeId::of::<A>(), context);
+    }
+
+    /// Push a context onto the top of the context stack, enabling its actions. If `consume` is set,
+    /// a switch which activates an action in this context will not activate actions in the
+    /// contexts beneath it. Pushing a context which is already on the stack moves it to the top.
+    pub fn push_context(&mut self, name: &'static str, consume: bool) {
+        self.context_stack.retain(|context| context.name != name);
+        self.context_stack.push(InputContext { name, consume });
+    }
+
+    /// Pop the topmost context, deactivating any of its actions which are active
+    pub fn pop_context(&mut self) -> Option<InputContext> {
+        let context = self.context_stack.pop();
+        self.deactivate_disabled();
+        context
+    }
+
+    /// Remove a context from anywhere in the stack
+    pub fn remove_context(&mut self, name: &'static str) {
+        self.context_stack.retain(|context| context.name != name);
+        self.deactivate_disabled();
+    }
+
+    pub fn context_enabled(&self, name: &'static str) -> bool {
+        self.context_stack
+            .iter()
+            .any(|context| context.name == name)
+    }
+
+    pub fn contexts(&self) -> &[InputContext] {
+        &self.context_stack
+    }
+
+    /// The position of an action's context in the stack, where higher levels take precedence.
+    /// Actions without a context are at level -1, beneath all contexts, and disabled actions
+    /// have no level.
+    fn context_level(&self, action: ActionId) -> Option<i32> {
+        match self.contexts.get(&action.0) {
+            Some(name) => self
+                .context_stack
+                .iter()
+                .rposition(|context| context.name == *name)
+                .map(|level| level as i32),
+            None => Some(-1),
+        }
+    }
+
+    fn context_consumes(&self, level: i32) -> bool {
+        level >= 0 && self.context_stack[level as usize].consume
+    }
+
+    fn deactivate_disabled(&mut self) {
+        let disabled: Vec<ActionId> = self
+            .active
+            .iter()
+            .copied()
+            .filter(|&action| self.context_level(action).is_none())
+            .collect();
+
+        for action in disabled {
+            self.active.remove(&action);
+            self.just_deactivated.insert(action);
+            self.cancelled.insert(action);
+        }
     }
 
     pub(crate) fn press(&mut self, key: Switch) {
+        let repeated = self
+            .nodes
+            .get(&[key].to_vec())
+            .map_or(false, |&index| self.bindings[index].active > 0);
+
+        self.advanced_sequences.clear();
+
+        // the logical modifier is pressed by the first of its physical keys
+        if let Switch::Key(key) = key {
+            if let Some(modifier) = Modifier::from_key(key) {
+                if self.modifier_keys.insert(key) && !self.other_modifier_key_held(key, modifier) {
+                    self.activate(Switch::Modifier(modifier));
+                }
+            }
+        }
+
         self.activate(key);
+
+        // only discrete presses abandon sequences; analog switches are pressed continuously
+        if let Switch::Key(_) | Switch::Mouse(_) | Switch::GamepadButton(_) = key {
+            if !repeated {
+                self.abandon_sequences(key);
+            }
+        }
     }
 
     pub fn just_activated(&self, key: impl Action) -> bool {
@@ -277,6 +1053,15 @@ impl MappedInput {
         self.just_deactivated.get(&key.to_id()).is_some()
     }
 
+    /// Whether an action was deactivated this frame by releasing its switches. Unlike
+    /// [`MappedInput::just_deactivated`], this excludes actions which were cancelled, e.g. by their
+    /// context being removed or by [`MappedInput::release_all`], so that actions completing on
+    /// release only complete when the player actually lets go.
+    pub fn just_released(&self, key: impl Action) -> bool {
+        let action = key.to_id();
+        self.just_deactivated.contains(&action) && !self.cancelled.contains(&action)
+    }
+
     pub fn active(&self, key: impl Action) -> bool {
         self.active.get(&key.to_id()).is_some()
     }
@@ -298,6 +1083,101 @@ impl MappedInput {
         }
     }
 
+    /// The value of the gamepad axis or analog button which activated an action
+    pub fn axis(&self, key: impl Action) -> Option<f32> {
+        let action = key.to_id();
+
+        if !self.active.contains(&action) {
+            return None;
+        }
+
+        match *self.action_sources.get(&action)? {
+            Switch::GamepadAxis(axis) => Some(self.axis_value(axis)),
+            Switch::GamepadButton(button) => {
+                Some(self.gamepad_buttons.get(&button).copied().unwrap_or(0.0))
+            }
+            _ => None,
+        }
+    }
+
+    /// The deflection of the gamepad stick which activated an action
+    pub fn stick(&self, key: impl Action) -> Option<Vec2> {
+        let action = key.to_id();
+
+        if !self.active.contains(&action) {
+            return None;
+        }
+
+        match *self.action_sources.get(&action)? {
+            Switch::GamepadStick(stick) => Some(self.stick_value(stick)),
+            _ => None,
+        }
+    }
+
+    pub fn set_dead_zones(&mut self, dead_zones: DeadZones) {
+        self.dead_zones = dead_zones;
+    }
+
+    fn axis_value(&self, axis: GamepadAxisType) -> f32 {
+        let value = self.gamepad_axes.get(&axis).copied().unwrap_or(0.0);
+        gamepad::apply_dead_zone(value, self.dead_zones.axis)
+    }
+
+    fn stick_value(&self, stick: Stick) -> Vec2 {
+        let (x, y) = match stick {
+            Stick::Left => (GamepadAxisType::LeftStickX, GamepadAxisType::LeftStickY),
+            Stick::Right => (GamepadAxisType::RightStickX, GamepadAxisType::RightStickY),
+        };
+
+        let value = Vec2::new(
+            self.gamepad_axes.get(&x).copied().unwrap_or(0.0),
+            self.gamepad_axes.get(&y).copied().unwrap_or(0.0),
+        );
+
+        gamepad::apply_radial_dead_zone(value, self.dead_zones.stick)
+    }
+
+    pub(crate) fn gamepad_button(&mut self, button: GamepadButtonType, value: f32) {
+        self.gamepad_buttons.insert(button, value);
+
+        if value >= self.dead_zones.button {
+            self.press(Switch::GamepadButton(button));
+        } else {
+            self.release(Switch::GamepadButton(button));
+        }
+    }
+
+    pub(crate) fn gamepad_axis(&mut self, axis: GamepadAxisType, value: f32) {
+        self.gamepad_axes.insert(axis, value);
+
+        if self.axis_value(axis) != 0.0 {
+            self.press(Switch::GamepadAxis(axis));
+        } else {
+            self.release(Switch::GamepadAxis(axis));
+        }
+
+        if let Some((stick, _)) = gamepad::stick_axis(axis) {
+            if self.stick_value(stick) != Vec2::ZERO {
+                self.press(Switch::GamepadStick(stick));
+            } else {
+                self.release(Switch::GamepadStick(stick));
+            }
+        }
+    }
+
+    /// Release every gamepad switch, e.g. when a gamepad is disconnected
+    pub(crate) fn release_gamepad(&mut self) {
+        let buttons: Vec<GamepadButtonType> = self.gamepad_buttons.keys().copied().collect();
+        for button in buttons {
+            self.gamepad_button(button, 0.0);
+        }
+
+        let axes: Vec<GamepadAxisType> = self.gamepad_axes.keys().copied().collect();
+        for axis in axes {
+            self.gamepad_axis(axis, 0.0);
+        }
+    }
+
     pub(crate) fn move_mouse(&mut self, motion: Vec2) {
         // todo: Think about the performance here
         self.activate(Switch::MouseMotion);
@@ -310,6 +1190,238 @@ impl MappedInput {
     }
     pub(crate) fn release(&mut self, key: Switch) {
         self.deactivate(key);
+
+        // and released by the last
+        if let Switch::Key(key) = key {
+            if let Some(modifier) = Modifier::from_key(key) {
+                if self.modifier_keys.remove(&key) && !self.other_modifier_key_held(key, modifier) {
+                    self.deactivate(Switch::Modifier(modifier));
+                }
+            }
+        }
+    }
+
+    fn other_modifier_key_held(&self, key: KeyCode, modifier: Modifier) -> bool {
+        modifier
+            .keys()
+            .iter()
+            .any(|other| *other != key && self.modifier_keys.contains(other))
+    }
+
+    pub(crate) fn move_cursor(&mut self, position: Option<Vec2>) {
+        self.cursor = position;
+    }
+
+    /// Seconds between the previous input frame and the current one. While a recording is played
+    /// back this is the recorded frame time, so systems which scale by it rather than by
+    /// `Time` play back the same way they were recorded.
+    pub fn delta_seconds(&self) -> f32 {
+        self.delta
+    }
+
+    /// The cursor position in window coordinates, as last fed into the input map
+    pub fn cursor_position(&self) -> Option<Vec2> {
+        self.cursor
+    }
+
+    /// Release every switch which is currently held, e.g. before and after playing back a
+    /// recording
+    pub(crate) fn release_all(&mut self) {
+        self.cancelling = true;
+
+        let modifier_keys: Vec<KeyCode> = self.modifier_keys.iter().copied().collect();
+        for key in modifier_keys {
+            self.release(Switch::Key(key));
+        }
+
+        let held: Vec<Switch> = self
+            .bindings
+            .node_indices()
+            .map(|index| &self.bindings[index])
+            .filter(|node| node.switches.len() == 1 && node.active > 0)
+            .map(|node| node.switches[0])
+            .collect();
+
+        for switch in held {
+            self.release(switch);
+        }
+
+        self.release_gamepad();
+        self.cancelling = false;
+    }
+
+    /// A frame which brings an input map with nothing held to the current state: the cursor
+    /// position, every held key and mouse button, and the gamepad's buttons and axes. Recordings
+    /// start with it, as the switches held when recording starts were pressed before it.
+    pub(crate) fn snapshot(&self) -> RecordedFrame {
+        let mut events = vec![match self.cursor {
+            Some(position) => InputEvent::Cursor(position),
+            None => InputEvent::CursorLeft,
+        }];
+
+        // physical modifier keys press their logical modifier when played back
+        events.extend(
+            self.modifier_keys
+                .iter()
+                .map(|&key| InputEvent::Press(Switch::Key(key))),
+        );
+
+        events.extend(
+            self.bindings
+                .node_indices()
+                .map(|index| &self.bindings[index])
+                .filter(|node| node.switches.len() == 1 && node.active > 0)
+                .filter_map(|node| match node.switches[0] {
+                    Switch::Key(key) if self.modifier_keys.contains(&key) => None,
+                    switch @ Switch::Key(_) | switch @ Switch::Mouse(_) => {
+                        Some(InputEvent::Press(switch))
+                    }
+                    _ => None,
+                }),
+        );
+
+        events.extend(
+            self.gamepad_buttons
+                .iter()
+                .filter(|(_, &value)| value != 0.0)
+                .map(|(&button, &value)| InputEvent::GamepadButton(button, value)),
+        );
+        events.extend(
+            self.gamepad_axes
+                .iter()
+                .filter(|(_, &value)| value != 0.0)
+                .map(|(&axis, &value)| InputEvent::GamepadAxis(axis, value)),
+        );
+
+        RecordedFrame {
+            time: self.now,
+            events,
+        }
+    }
+
+    /// Feed a single event into the input map
+    pub(crate) fn apply(&mut self, event: InputEvent) {
+        match event {
+            InputEvent::Press(switch) => self.press(switch),
+            InputEvent::Release(switch) => self.release(switch),
+            InputEvent::MouseMotion(motion) => self.move_mouse(motion),
+            InputEvent::MouseScroll(scroll) => self.scroll_mouse(scroll),
+            InputEvent::Cursor(position) => self.move_cursor(Some(position)),
+            InputEvent::CursorLeft => self.move_cursor(None),
+            InputEvent::GamepadButton(button, value) => self.gamepad_button(button, value),
+            InputEvent::GamepadAxis(axis, value) => self.gamepad_axis(axis, value),
+            InputEvent::GamepadDisconnected => self.release_gamepad(),
+        }
+    }
+
+    /// Begin a new frame and feed it the frame's events, as done by the input system every frame
+    pub fn apply_frame(&mut self, frame: &RecordedFrame) {
+        self.update(frame.time);
+
+        for &event in &frame.events {
+            self.apply(event);
+        }
+    }
+
+    /// Every binding currently held, as the full switch combination (layer followed by the
+    /// terminating switch) and the action it is bound to
+    fn all_bindings(&self) -> Vec<(Vec<Switch>, ActionId, Gesture)> {
+        self.bindings
+            .edge_references()
+            .filter_map(|edge| match *edge.weight() {
+                Edge::Action(action, gesture) => {
+                    let mut switches = self.bindings[edge.target()].switches.clone();
+                    switches.extend_from_slice(&self.bindings[edge.source()].switches);
+                    Some((switches, action, gesture))
+                }
+                Edge::Step(..) | Edge::Layer => None,
+            })
+            .collect()
+    }
+
+    /// Export the current bindings as a keymap
+    pub fn keymap(&self) -> Keymap {
+        let mut bindings: Vec<KeymapEntry> = self
+            .all_bindings()
+            .into_iter()
+            .map(|(switches, action, gesture)| KeymapEntry {
+                action: self.action_name(action),
+                switches,
+                gesture,
+            })
+            .collect();
+
+        bindings.sort_by(|a, b| a.action.cmp(&b.action));
+
+        Keymap { bindings }
+    }
+
+    /// Rebuild the binding graph from a keymap. Any held switches are released first.
+    ///
+    /// Every action mentioned in the keymap loses its current bindings in favour of those in the
+    /// keymap; all other actions keep their current bindings. Invalid entries are skipped and
+    /// reported in the returned errors.
+    pub fn apply_keymap(&mut self, keymap: &Keymap) -> Vec<KeymapError> {
+        // the rebuilt graph starts with nothing held, so release everything first rather than
+        // leaving actions of held switches stuck active
+        self.release_all();
+
+        let mut errors = Vec::new();
+        let mut overrides = Vec::new();
+
+        for entry in &keymap.bindings {
+            let action = match self.named_actions.get(&entry.action) {
+                Some(&action) => action,
+                None => {
+                    errors.push(KeymapError::UnknownAction(entry.action.clone()));
+                    continue;
+                }
+            };
+
+            if entry.switches.is_empty() {
+                errors.push(KeymapError::EmptyBinding(entry.action.clone()));
+                continue;
+            }
+
+            let mut seen = HashSet::new();
+            if let Some(&repeated) = entry.switches.iter().find(|&&s| !seen.insert(s)) {
+                errors.push(KeymapError::RepeatedSwitch(entry.action.clone(), repeated));
+                continue;
+            }
+
+            overrides.push((entry.switches.clone(), action, entry.gesture));
+        }
+
+        let overridden: HashSet<ActionId> =
+            overrides.iter().map(|&(_, action, _)| action).collect();
+
+        let mut bindings: Vec<(Vec<Switch>, ActionId, Gesture)> = self
+            .all_bindings()
+            .into_iter()
+            .filter(|(_, action, _)| !overridden.contains(action))
+            .collect();
+        bindings.extend(overrides);
+
+        self.bindings.clear();
+        self.nodes.clear();
+        self.edge_labels.clear();
+        self.node_labels.clear();
+        self.held_gestures.clear();
+        self.last_presses.clear();
+
+        for (switches, action, gesture) in bindings {
+            debug!("Binding {:?} to {}", switches, self.action_name(action));
+            self.bind_switches(&switches, action, gesture);
+        }
+
+        // sequences are not part of keymaps, so they are restored as they were
+        let sequences: Vec<SequenceId> = self.sequences.keys().copied().collect();
+        for id in sequences {
+            self.sequences.get_mut(&id).unwrap().progress = 0;
+            self.add_sequence_edges(id);
+        }
+
+        errors
     }
 
     pub(crate) fn bindings_graphviz(&self) -> String {
@@ -319,10 +1431,431 @@ impl MappedInput {
 
         format!("{}", petgraph::dot::Dot::new(&debug_graph))
     }
+
+    /// Summarise every binding and sequence, grouped by action enum
+    pub fn cheat_sheet(&self) -> CheatSheet {
+        let bindings = self
+            .all_bindings()
+            .into_iter()
+            .map(|(switches, action, gesture)| {
+                (
+                    self.action_name(action),
+                    cheatsheet::describe_binding(&switches, gesture),
+                )
+            });
+
+        let sequences = self.sequences.values().map(|sequence| {
+            (
+                self.action_name(sequence.action),
+                cheatsheet::describe_sequence(&sequence.steps),
+            )
+        });
+
+        CheatSheet::new(bindings.chain(sequences))
+    }
 }
 
-pub(crate) fn debug_binding_graph(input: &MappedInput) {
-    let mut file = std::fs::File::create("bindings.dot").expect("Failed to create file");
-    std::io::Write::write_all(&mut file, input.bindings_graphviz().as_bytes())
-        .expect("Failed to write dot");
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::prelude::MouseButton;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, num_derive::ToPrimitive)]
+    enum Timed {
+        Tap,
+        DoubleTap,
+        LongPress,
+        Repeat,
+    }
+
+    #[test]
+    fn tap_released_too_late() {
+        let mut inputs = MappedInput::default();
+        inputs.bind_gesture([KeyCode::T], Timed::Tap, Gesture::Tap { max: 0.2 });
+
+        inputs.update(1.0);
+        inputs.press(KeyCode::T.into());
+        inputs.update(1.1);
+        inputs.release(KeyCode::T.into());
+        assert!(inputs.just_activated(Timed::Tap));
+
+        // the tap only lasts a single frame
+        inputs.update(1.2);
+        assert!(inputs.just_deactivated(Timed::Tap));
+
+        inputs.update(2.0);
+        inputs.press(KeyCode::T.into());
+        inputs.update(2.3);
+        inputs.release(KeyCode::T.into());
+        assert!(!inputs.just_activated(Timed::Tap));
+        assert!(!inputs.active(Timed::Tap));
+    }
+
+    #[test]
+    fn double_tap_outside_the_window() {
+        let mut inputs = MappedInput::default();
+        inputs.bind_gesture(
+            [KeyCode::D],
+            Timed::DoubleTap,
+            Gesture::DoubleTap { window: 0.3 },
+        );
+
+        let tap = |inputs: &mut MappedInput, now: f64| {
+            inputs.update(now);
+            inputs.press(KeyCode::D.into());
+            inputs.update(now + 0.05);
+            inputs.release(KeyCode::D.into());
+        };
+
+        tap(&mut inputs, 1.0);
+        tap(&mut inputs, 1.5);
+        assert!(!inputs.active(Timed::DoubleTap));
+
+        // the late press starts a new double tap, which the next press completes
+        inputs.update(1.7);
+        inputs.press(KeyCode::D.into());
+        assert!(inputs.just_activated(Timed::DoubleTap));
+    }
+
+    #[test]
+    fn long_press_released_before_threshold() {
+        let mut inputs = MappedInput::default();
+        inputs.bind_gesture(
+            [KeyCode::L],
+            Timed::LongPress,
+            Gesture::LongPress { threshold: 0.5 },
+        );
+
+        inputs.update(1.0);
+        inputs.press(KeyCode::L.into());
+        inputs.update(1.4);
+        inputs.release(KeyCode::L.into());
+        inputs.update(1.6);
+        assert!(!inputs.just_activated(Timed::LongPress));
+        assert!(!inputs.active(Timed::LongPress));
+
+        inputs.update(2.0);
+        inputs.press(KeyCode::L.into());
+        inputs.update(2.4);
+        assert!(!inputs.active(Timed::LongPress));
+        inputs.update(2.5);
+        assert!(inputs.just_activated(Timed::LongPress));
+    }
+
+    #[test]
+    fn repeat_cadence() {
+        let mut inputs = MappedInput::default();
+        inputs.bind_gesture(
+            [KeyCode::R],
+            Timed::Repeat,
+            Gesture::Repeat {
+                delay: 0.5,
+                rate: 4.0,
+            },
+        );
+
+        inputs.update(0.0);
+        inputs.press(KeyCode::R.into());
+        assert!(inputs.just_activated(Timed::Repeat));
+
+        let repeats: Vec<bool> = [0.25, 0.5, 0.6, 0.75, 1.0, 1.1]
+            .iter()
+            .map(|&now| {
+                inputs.update(now);
+                inputs.just_activated(Timed::Repeat)
+            })
+            .collect();
+        assert_eq!(repeats, vec![false, true, false, true, true, false]);
+
+        inputs.release(KeyCode::R.into());
+        inputs.update(1.25);
+        assert!(!inputs.just_activated(Timed::Repeat));
+        assert!(!inputs.active(Timed::Repeat));
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, num_derive::ToPrimitive)]
+    enum Combo {
+        QuickSave,
+        Walk,
+    }
+
+    /// Press and release a key at `now`
+    fn stroke(inputs: &mut MappedInput, key: KeyCode, now: f64) {
+        inputs.update(now);
+        inputs.press(key.into());
+        inputs.release(key.into());
+    }
+
+    fn quick_save() -> MappedInput {
+        let mut inputs = MappedInput::default();
+        inputs.bind_sequence([[KeyCode::Q], [KeyCode::W]], Combo::QuickSave, 0.5);
+        inputs
+    }
+
+    #[test]
+    fn sequence_completes() {
+        let mut inputs = quick_save();
+
+        stroke(&mut inputs, KeyCode::Q, 1.0);
+        assert!(inputs.sequence_pending(Combo::QuickSave));
+
+        inputs.update(1.2);
+        inputs.press(KeyCode::W.into());
+        assert!(inputs.just_activated(Combo::QuickSave));
+        assert!(!inputs.sequence_pending(Combo::QuickSave));
+
+        inputs.update(1.3);
+        assert!(inputs.active(Combo::QuickSave));
+        inputs.release(KeyCode::W.into());
+        assert!(inputs.just_deactivated(Combo::QuickSave));
+    }
+
+    #[test]
+    fn sequence_times_out() {
+        let mut inputs = quick_save();
+
+        stroke(&mut inputs, KeyCode::Q, 1.0);
+        inputs.update(1.6);
+        assert!(!inputs.sequence_pending(Combo::QuickSave));
+
+        stroke(&mut inputs, KeyCode::W, 1.7);
+        assert!(!inputs.active(Combo::QuickSave));
+        assert!(!inputs.just_activated(Combo::QuickSave));
+    }
+
+    #[test]
+    fn interrupting_key_resets_sequence() {
+        let mut inputs = quick_save();
+
+        stroke(&mut inputs, KeyCode::Q, 1.0);
+        stroke(&mut inputs, KeyCode::E, 1.1);
+        assert!(!inputs.sequence_pending(Combo::QuickSave));
+
+        stroke(&mut inputs, KeyCode::W, 1.2);
+        assert!(!inputs.just_activated(Combo::QuickSave));
+
+        // starting over completes as usual
+        stroke(&mut inputs, KeyCode::Q, 1.3);
+        stroke(&mut inputs, KeyCode::W, 1.4);
+        assert!(inputs.just_activated(Combo::QuickSave));
+    }
+
+    #[test]
+    fn sequence_step_also_bound_alone() {
+        let mut inputs = quick_save();
+        inputs.bind([KeyCode::W], Combo::Walk);
+
+        stroke(&mut inputs, KeyCode::W, 1.0);
+        assert!(inputs.just_activated(Combo::Walk));
+        assert!(!inputs.just_activated(Combo::QuickSave));
+
+        stroke(&mut inputs, KeyCode::Q, 2.0);
+        stroke(&mut inputs, KeyCode::W, 2.1);
+        assert!(inputs.just_activated(Combo::QuickSave));
+        assert!(inputs.just_activated(Combo::Walk));
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, num_derive::ToPrimitive)]
+    enum Modified {
+        Sprint,
+    }
+
+    #[test]
+    fn modifier_held_by_either_key() {
+        let mut inputs = MappedInput::default();
+        inputs.bind([Modifier::Shift], Modified::Sprint);
+
+        inputs.update(1.0);
+        inputs.press(KeyCode::LShift.into());
+        inputs.press(KeyCode::RShift.into());
+        assert!(inputs.just_activated(Modified::Sprint));
+
+        inputs.update(1.1);
+        inputs.release(KeyCode::LShift.into());
+        assert!(inputs.active(Modified::Sprint));
+        assert!(!inputs.just_deactivated(Modified::Sprint));
+
+        inputs.update(1.2);
+        inputs.release(KeyCode::RShift.into());
+        assert!(inputs.just_deactivated(Modified::Sprint));
+        assert!(!inputs.active(Modified::Sprint));
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, num_derive::ToPrimitive)]
+    enum Units {
+        Select,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, num_derive::ToPrimitive)]
+    enum Map {
+        Focus,
+    }
+
+    /// Both actions bound to the left mouse button, with the map context above the units context
+    fn layered_contexts(consume: bool) -> MappedInput {
+        let mut inputs = MappedInput::default();
+        inputs.assign_context::<Units>("Units");
+        inputs.assign_context::<Map>("Map");
+        inputs.bind([MouseButton::Left], Units::Select);
+        inputs.bind([MouseButton::Left], Map::Focus);
+        inputs.push_context("Units", false);
+        inputs.push_context("Map", consume);
+        inputs
+    }
+
+    #[test]
+    fn consuming_context_blocks_lower_contexts() {
+        let mut inputs = layered_contexts(true);
+
+        inputs.update(1.0);
+        inputs.press(MouseButton::Left.into());
+        assert!(inputs.just_activated(Map::Focus));
+        assert!(!inputs.active(Units::Select));
+    }
+
+    #[test]
+    fn non_consuming_context_passes_through() {
+        let mut inputs = layered_contexts(false);
+
+        inputs.update(1.0);
+        inputs.press(MouseButton::Left.into());
+        assert!(inputs.just_activated(Map::Focus));
+        assert!(inputs.just_activated(Units::Select));
+    }
+
+    #[test]
+    fn removing_context_deactivates_held_actions() {
+        let mut inputs = layered_contexts(true);
+
+        inputs.update(1.0);
+        inputs.press(MouseButton::Left.into());
+        inputs.update(1.1);
+        inputs.remove_context("Map");
+        assert!(!inputs.active(Map::Focus));
+        assert!(inputs.just_deactivated(Map::Focus));
+        assert!(!inputs.just_released(Map::Focus));
+
+        // releasing the button afterwards doesn't complete the action either
+        inputs.update(1.2);
+        inputs.release(MouseButton::Left.into());
+        assert!(!inputs.just_deactivated(Map::Focus));
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, num_derive::ToPrimitive)]
+    enum Bound {
+        Jump,
+        Crouch,
+    }
+
+    /// Every label belongs to a node or edge in the graph and vice versa, and no state is kept
+    /// for edges which have been removed
+    fn assert_consistent(inputs: &MappedInput) {
+        let graph = &inputs.bindings;
+
+        assert_eq!(inputs.edge_labels.len(), graph.edge_count());
+        assert!(graph
+            .edge_indices()
+            .all(|e| inputs.edge_labels.contains_key(&e)));
+        assert_eq!(inputs.node_labels.len(), graph.node_count());
+        assert!(graph
+            .node_indices()
+            .all(|n| inputs.node_labels.contains_key(&n)));
+        assert!(inputs.nodes.values().all(|&n| graph.contains_node(n)));
+        assert!(inputs
+            .held_gestures
+            .keys()
+            .chain(inputs.last_presses.keys())
+            .all(|&e| graph.edge_weight(e).is_some()));
+    }
+
+    #[test]
+    fn unbind_and_rebind_while_held() {
+        let mut inputs = MappedInput::default();
+        inputs.bind([KeyCode::Space], Bound::Jump);
+
+        inputs.update(1.0);
+        inputs.press(KeyCode::Space.into());
+        inputs.unbind([KeyCode::Space], Bound::Jump);
+        assert!(inputs.just_deactivated(Bound::Jump));
+        assert!(!inputs.just_released(Bound::Jump));
+        assert_consistent(&inputs);
+
+        // binding a held key doesn't activate it, and releasing it doesn't deactivate anything
+        inputs.update(1.1);
+        inputs.bind([KeyCode::Space], Bound::Jump);
+        assert!(!inputs.active(Bound::Jump));
+        inputs.release(KeyCode::Space.into());
+        assert!(!inputs.just_deactivated(Bound::Jump));
+
+        inputs.update(1.2);
+        inputs.press(KeyCode::Space.into());
+        assert!(inputs.just_activated(Bound::Jump));
+        assert_consistent(&inputs);
+    }
+
+    #[test]
+    fn bind_layer_while_held() {
+        // only switches which are bound are tracked while held
+        let mut inputs = MappedInput::default();
+        inputs.bind([KeyCode::LControl, KeyCode::A], Bound::Jump);
+        inputs.bind([KeyCode::LAlt, KeyCode::A], Bound::Jump);
+
+        inputs.update(1.0);
+        inputs.press(KeyCode::LControl.into());
+        inputs.press(KeyCode::LAlt.into());
+        inputs.bind(
+            [KeyCode::LControl, KeyCode::LAlt, KeyCode::C],
+            Bound::Crouch,
+        );
+
+        inputs.update(1.1);
+        inputs.press(KeyCode::C.into());
+        assert!(inputs.just_activated(Bound::Crouch));
+
+        inputs.update(1.2);
+        inputs.release(KeyCode::C.into());
+        inputs.release(KeyCode::LAlt.into());
+        inputs.release(KeyCode::LControl.into());
+
+        inputs.update(1.3);
+        inputs.press(KeyCode::C.into());
+        assert!(!inputs.active(Bound::Crouch));
+    }
+
+    #[test]
+    fn orphaned_layers_are_removed() {
+        let mut inputs = MappedInput::default();
+        inputs.bind_gesture(
+            [KeyCode::LControl, KeyCode::LAlt, KeyCode::A],
+            Bound::Jump,
+            Gesture::Tap { max: 1.0 },
+        );
+
+        inputs.update(1.0);
+        inputs.press(KeyCode::LControl.into());
+        inputs.press(KeyCode::LAlt.into());
+        inputs.press(KeyCode::A.into());
+        assert_eq!(inputs.held_gestures.len(), 1);
+
+        inputs.unbind([KeyCode::LControl, KeyCode::LAlt, KeyCode::A], Bound::Jump);
+        assert!(inputs.held_gestures.is_empty());
+        assert!(inputs.nodes.keys().all(|switches| switches.len() == 1));
+        assert_consistent(&inputs);
+
+        // the new binding reuses the removed indices, and must not inherit the old tap
+        inputs.bind(
+            [KeyCode::LControl, KeyCode::LAlt, KeyCode::B],
+            Bound::Crouch,
+        );
+        assert_consistent(&inputs);
+
+        inputs.update(1.1);
+        inputs.release(KeyCode::A.into());
+        assert!(!inputs.just_activated(Bound::Jump));
+        assert!(!inputs.active(Bound::Crouch));
+
+        inputs.press(KeyCode::B.into());
+        assert!(inputs.just_activated(Bound::Crouch));
+        assert_consistent(&inputs);
+    }
 }
@@ -0,0 +1,187 @@
+//! Keymap files allow the bindings held by a [`MappedInput`](super::MappedInput) to be edited
+//! without recompiling.
+//!
+//! A keymap is a RON file listing switch combinations against action names, where an action name
+//! is the name of the action enum followed by the variant, e.g.
+//!
+//! ```ron
+//! (
+//!     bindings: [
+//!         (action: "Pan::Left", switches: [Key(A)]),
+//!         (action: "Controls::Orbit", switches: [Mouse(Right), MouseMotion]),
+//!         (action: "Orders::Stop", switches: [Key(S)], gesture: LongPress(threshold: 0.5)),
+//!     ],
+//! )
+//! ```
+//!
+//! Any action mentioned in the keymap has all of its default bindings replaced by the bindings
+//! listed in the file. Actions which the file does not mention keep their defaults.
+use super::{Gesture, Switch};
+use crate::ron_file::{self, RonFileError};
+use serde::{Deserialize, Serialize};
+use std::{error::Error, fmt, path::Path};
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct Keymap {
+    pub bindings: Vec<KeymapEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct KeymapEntry {
+    pub action: String,
+    pub switches: Vec<Switch>,
+    #[serde(default)]
+    pub gesture: Gesture,
+}
+
+/// An entry of a keymap which can't be applied
+#[derive(Debug)]
+pub enum KeymapError {
+    UnknownAction(String),
+    EmptyBinding(String),
+    RepeatedSwitch(String, Switch),
+}
+
+impl fmt::Display for KeymapError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            KeymapError::UnknownAction(action) => write!(f, "unknown action `{}`", action),
+            KeymapError::EmptyBinding(action) => {
+                write!(f, "binding for `{}` has no switches", action)
+            }
+            KeymapError::RepeatedSwitch(action, switch) => write!(
+                f,
+                "binding for `{}` lists switch {} more than once",
+                action, switch
+            ),
+        }
+    }
+}
+
+impl Error for KeymapError {}
+
+impl Keymap {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, RonFileError> {
+        ron_file::load(path)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), RonFileError> {
+        ron_file::save(self, path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::{MappedInput, Modifier};
+    use bevy::prelude::{KeyCode, MouseButton};
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, num_derive::ToPrimitive)]
+    enum Pan {
+        Left,
+        Right,
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, num_derive::ToPrimitive)]
+    enum Orders {
+        Stop,
+        Move,
+    }
+
+    fn defaults() -> MappedInput {
+        let mut inputs = MappedInput::default();
+        inputs.bind([KeyCode::A], Pan::Left);
+        inputs.bind([KeyCode::Left], Pan::Left);
+        inputs.bind([KeyCode::D], Pan::Right);
+        inputs.bind_gesture(
+            [KeyCode::S],
+            Orders::Stop,
+            Gesture::LongPress { threshold: 0.5 },
+        );
+        inputs.bind(
+            [Switch::from(Modifier::Shift), MouseButton::Right.into()],
+            Orders::Move,
+        );
+        inputs
+    }
+
+    /// The bindings of a keymap in a comparable order
+    fn entries(keymap: &Keymap) -> Vec<(String, Vec<Switch>, Gesture)> {
+        let mut entries: Vec<_> = keymap
+            .bindings
+            .iter()
+            .map(|entry| (entry.action.clone(), entry.switches.clone(), entry.gesture))
+            .collect();
+        entries.sort_by_key(|(action, switches, _)| (action.clone(), format!("{:?}", switches)));
+        entries
+    }
+
+    #[test]
+    fn round_trip() {
+        let exported = defaults().keymap();
+
+        let path = std::env::temp_dir().join(format!("keymap-{}.ron", std::process::id()));
+        exported.save(&path).unwrap();
+        let loaded = Keymap::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        let loaded = loaded.unwrap();
+
+        // applied over different bindings, the keymap restores the exported ones
+        let mut inputs = defaults();
+        inputs.rebind([KeyCode::J], Pan::Left);
+        inputs.rebind([KeyCode::K], Orders::Stop);
+
+        assert!(inputs.apply_keymap(&loaded).is_empty());
+        assert_eq!(entries(&inputs.keymap()), entries(&exported));
+    }
+
+    #[test]
+    fn unknown_actions_are_reported() {
+        let keymap: Keymap = ron::from_str(
+            r#"(
+                bindings: [
+                    (action: "Pan::Up", switches: [Key(W)]),
+                    (action: "Pan::Right", switches: [Key(L)]),
+                ],
+            )"#,
+        )
+        .unwrap();
+
+        let mut inputs = defaults();
+        let errors = inputs.apply_keymap(&keymap);
+
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(&errors[0], KeymapError::UnknownAction(action) if action == "Pan::Up"));
+        assert_eq!(
+            inputs.bindings_for(Pan::Right),
+            vec![vec![Switch::from(KeyCode::L)]]
+        );
+    }
+
+    #[test]
+    fn missing_actions_keep_defaults() {
+        let keymap = Keymap {
+            bindings: vec![KeymapEntry {
+                action: "Pan::Left".to_string(),
+                switches: vec![KeyCode::J.into()],
+                gesture: Gesture::Press,
+            }],
+        };
+
+        let mut inputs = defaults();
+        assert!(inputs.apply_keymap(&keymap).is_empty());
+
+        assert_eq!(
+            inputs.bindings_for(Pan::Left),
+            vec![vec![Switch::from(KeyCode::J)]]
+        );
+
+        let others = |keymap: &Keymap| {
+            entries(keymap)
+                .into_iter()
+                .filter(|(action, _, _)| action != "Pan::Left")
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(others(&inputs.keymap()), others(&defaults().keymap()));
+    }
+}
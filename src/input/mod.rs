@@ -16,25 +16,22 @@
 //! }
 //!
 //! fn setup_debug_input(mut inputs: ResMut<MappedInput>) {
+//!     // either alt key
 //!     inputs.bind(
-//!         [KeyCode::LAlt, KeyCode::A],
+//!         [Switch::from(Modifier::Alt), KeyCode::A.into()],
 //!         SomeKeyBindings::SomeModifiedAction,
 //!     );
 //!     inputs.bind(
-//!         [KeyCode::LAlt.into(), Switch::from(MouseButton::Left)],
+//!         [Switch::from(Modifier::Alt), MouseButton::Left.into()],
 //!         SomeKeyBindings::SomeModifiedAction,
 //!     );
 //!
 //!     inputs.bind(
-//!         [KeyCode::RAlt, KeyCode::RControl, KeyCode::A],
+//!         [Switch::from(Modifier::Alt), Modifier::Ctrl.into(), KeyCode::A.into()],
 //!         SomeKeyBindings::SomeDoubleModifiedAction,
 //!     );
 //!
-//!     inputs.bind(
-//!         [KeyCode::RAlt, KeyCode::A],
-//!         SomeKeyBindings::SomeModifiedAction,
-//!     );
-//!
+//!     // only the right control key
 //!     inputs.bind(
 //!         [KeyCode::RControl, KeyCode::A],
 //!         SomeKeyBindings::SomeOtherModifiedAction,
@@ -48,12 +45,30 @@ use crate::SystemLabels;
 use bevy::input::mouse::{MouseMotion, MouseWheel};
 use bevy::prelude::*;
 use bevy::render::camera::Camera;
+use serde::{Deserialize, Serialize};
 use std::fmt;
 use std::fmt::Debug;
+use std::path::PathBuf;
 
+mod cheatsheet;
+mod diagnostics;
+mod gamepad;
+mod gesture;
 mod inputmap;
+mod keymap;
+mod modifier;
+mod recording;
+mod sequence;
 
-pub(crate) use inputmap::{debug_binding_graph, MappedInput};
+pub use cheatsheet::{CheatSheet, CheatSheetEntry, CheatSheetSection};
+pub use diagnostics::{BindingDiagnostic, Severity};
+pub use gamepad::{DeadZones, Stick};
+pub use gesture::Gesture;
+pub(crate) use inputmap::MappedInput;
+pub use keymap::{Keymap, KeymapError};
+pub use modifier::Modifier;
+pub use recording::{InputEvent, InputRecorder, RecordedFrame, RecorderControls, Recording};
+pub use sequence::SequenceProgress;
 
 pub struct InputPlugin;
 
@@ -61,18 +76,57 @@ impl Plugin for InputPlugin {
     fn build(&self, app: &mut AppBuilder) {
         app
             //.add_startup_system(input_setup.system())
+            // default bindings are made during Startup, so the keymap is applied over them after
+            .add_startup_system(setup_debug_controls.system())
+            .add_startup_system_to_stage(StartupStage::PostStartup, load_keymap.system())
             .add_system(input_handling.system().label(SystemLabels::Input))
-            .add_system(debug_input.system().after(SystemLabels::Input))
-            .insert_resource(MappedInput::default());
+            .add_system(recorder_controls.system().after(SystemLabels::Input))
+            .add_system(export_bindings.system().after(SystemLabels::Input))
+            .insert_resource(MappedInput::default())
+            .insert_resource(InputRecorder::default())
+            .insert_resource(InputConfig::default());
+    }
+}
+
+pub struct InputConfig {
+    /// Keymap file applied over the default bindings at startup
+    pub keymap: PathBuf,
+    /// Log binding conflicts once the keymap has been applied
+    pub log_diagnostics: bool,
+    /// File input recordings are saved to and played back from
+    pub recording: PathBuf,
+    /// File the binding graph is exported to, in graphviz dot format
+    pub graphviz: PathBuf,
+    /// File the binding cheat sheet is exported to, as markdown
+    pub cheat_sheet: PathBuf,
+    /// File the current bindings are exported to as a keymap, to copy entries into the keymap from
+    pub keymap_export: PathBuf,
+}
+
+impl Default for InputConfig {
+    fn default() -> Self {
+        InputConfig {
+            keymap: PathBuf::from("keymap.ron"),
+            log_diagnostics: true,
+            recording: PathBuf::from("recording.ron"),
+            graphviz: PathBuf::from("bindings.dot"),
+            cheat_sheet: PathBuf::from("bindings.md"),
+            keymap_export: PathBuf::from("keymap.export.ron"),
+        }
     }
 }
 
-#[derive(Debug, Hash, PartialEq, Eq, Copy, Clone)]
+#[derive(Debug, Hash, PartialEq, Eq, Copy, Clone, Serialize, Deserialize)]
 pub enum Switch {
     Key(KeyCode),
     Mouse(MouseButton),
     MouseMotion,
     MouseScroll,
+    GamepadButton(GamepadButtonType),
+    GamepadAxis(GamepadAxisType),
+    GamepadStick(Stick),
+    /// Either the left or right key of a modifier
+    Modifier(Modifier),
 }
 
 impl fmt::Display for Switch {
@@ -80,6 +134,10 @@ impl fmt::Display for Switch {
         match &self {
             Switch::Key(k) => write!(f, "{:?}", k),
             Switch::Mouse(k) => write!(f, "Mouse{:?}", k),
+            Switch::GamepadButton(b) => write!(f, "Gamepad{:?}", b),
+            Switch::GamepadAxis(a) => write!(f, "Gamepad{:?}", a),
+            Switch::GamepadStick(s) => write!(f, "Gamepad{:?}Stick", s),
+            Switch::Modifier(m) => write!(f, "{:?}", m),
             s => write!(f, "{:?}", s), //Switch::Mouse(b) => write!(f, "{:?}", b),
         }
     }
@@ -97,14 +155,46 @@ impl From<KeyCode> for Switch {
     }
 }
 
+impl From<GamepadButtonType> for Switch {
+    fn from(button: GamepadButtonType) -> Self {
+        Switch::GamepadButton(button)
+    }
+}
+
+impl From<GamepadAxisType> for Switch {
+    fn from(axis: GamepadAxisType) -> Self {
+        Switch::GamepadAxis(axis)
+    }
+}
+
+impl From<Stick> for Switch {
+    fn from(stick: Stick) -> Self {
+        Switch::GamepadStick(stick)
+    }
+}
+
+impl From<Modifier> for Switch {
+    fn from(modifier: Modifier) -> Self {
+        Switch::Modifier(modifier)
+    }
+}
+
 fn input_handling(
+    time: Res<Time>,
     mut inputs: ResMut<MappedInput>,
+    mut recorder: ResMut<InputRecorder>,
     mut keyboard_input: EventReader<bevy::input::keyboard::KeyboardInput>,
     mut mouse_button: EventReader<bevy::input::mouse::MouseButtonInput>,
     mut mouse_motion: EventReader<MouseMotion>,
     mut mouse_scroll: EventReader<MouseWheel>,
+    mut cursor_moved: EventReader<CursorMoved>,
+    mut cursor_left: EventReader<CursorLeft>,
+    mut gamepad_events: EventReader<GamepadEvent>,
 ) {
-    inputs.update();
+    let mut frame = RecordedFrame {
+        time: time.seconds_since_startup(),
+        events: Vec::new(),
+    };
 
     for event in keyboard_input.iter() {
         if let &bevy::input::keyboard::KeyboardInput {
@@ -113,29 +203,521 @@ fn input_handling(
             ..
         } = event
         {
-            match state {
-                bevy::input::ElementState::Pressed => inputs.press(key_code.into()),
-                bevy::input::ElementState::Released => inputs.release(key_code.into()),
-            }
+            frame.events.push(match state {
+                bevy::input::ElementState::Pressed => InputEvent::Press(key_code.into()),
+                bevy::input::ElementState::Released => InputEvent::Release(key_code.into()),
+            });
         }
     }
 
     for event in mouse_button.iter() {
-        match event.state {
-            bevy::input::ElementState::Pressed => inputs.press(event.button.into()),
-            bevy::input::ElementState::Released => inputs.release(event.button.into()),
-        }
+        frame.events.push(match event.state {
+            bevy::input::ElementState::Pressed => InputEvent::Press(event.button.into()),
+            bevy::input::ElementState::Released => InputEvent::Release(event.button.into()),
+        });
     }
 
     for event in mouse_motion.iter() {
-        inputs.move_mouse(event.delta);
+        frame.events.push(InputEvent::MouseMotion(event.delta));
     }
 
     for scroll in mouse_scroll.iter() {
-        inputs.scroll_mouse(scroll.y);
+        frame.events.push(InputEvent::MouseScroll(scroll.y));
+    }
+
+    for event in cursor_moved.iter() {
+        frame.events.push(InputEvent::Cursor(event.position));
+    }
+
+    if cursor_left.iter().count() > 0 {
+        frame.events.push(InputEvent::CursorLeft);
+    }
+
+    for GamepadEvent(_, event) in gamepad_events.iter() {
+        frame.events.push(match *event {
+            GamepadEventType::ButtonChanged(button, value) => {
+                InputEvent::GamepadButton(button, value)
+            }
+            GamepadEventType::AxisChanged(axis, value) => InputEvent::GamepadAxis(axis, value),
+            GamepadEventType::Disconnected => InputEvent::GamepadDisconnected,
+            GamepadEventType::Connected => continue,
+        });
+    }
+
+    if recorder.is_playing() {
+        // pressing the playback control for real stops playback early
+        let play_controls = inputs.bindings_for(RecorderControls::Play);
+        let stopped = frame.events.iter().any(|event| match event {
+            InputEvent::Press(switch) => {
+                play_controls.iter().any(|keys| keys.last() == Some(switch))
+            }
+            _ => false,
+        });
+
+        if stopped {
+            log::info!("Playback stopped");
+            recorder.stop_playback();
+            inputs.update(frame.time);
+            inputs.release_all();
+            return;
+        }
+
+        match recorder.next_frame(frame.time) {
+            Some(recorded) => {
+                inputs.apply_frame(&recorded);
+                return;
+            }
+            None => {
+                log::info!("Playback finished");
+                inputs.release_all();
+            }
+        }
+    }
+
+    recorder.record(&frame);
+    inputs.apply_frame(&frame);
+}
+
+/// Debugging actions for the input map itself
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, num_derive::ToPrimitive)]
+pub enum InputDebugControls {
+    /// Write the binding graph, the binding cheat sheet and the current bindings as a keymap to
+    /// the files set in [`InputConfig`]
+    ExportBindings,
+}
+
+fn setup_debug_controls(mut inputs: ResMut<MappedInput>) {
+    inputs.bind([KeyCode::F8], InputDebugControls::ExportBindings);
+    inputs.bind([KeyCode::F9], RecorderControls::Record);
+    inputs.bind([KeyCode::F10], RecorderControls::Play);
+}
+
+/// Starts and stops recording and playback. The controls are ignored while a recording is being
+/// played back, as the recording contains the press which stopped it.
+fn recorder_controls(
+    config: Res<InputConfig>,
+    mut inputs: ResMut<MappedInput>,
+    mut recorder: ResMut<InputRecorder>,
+) {
+    if recorder.is_playing() {
+        return;
+    }
+
+    if inputs.just_activated(RecorderControls::Record) {
+        match recorder.stop_recording() {
+            Some(recording) => match recording.save(&config.recording) {
+                Ok(()) => log::info!(
+                    "Saved {:.1}s recording to {}",
+                    recording.duration(),
+                    config.recording.display()
+                ),
+                Err(e) => log::error!("{}", e),
+            },
+            None => {
+                log::info!("Recording input");
+                recorder.start_recording(&inputs);
+            }
+        }
+    } else if inputs.just_activated(RecorderControls::Play) {
+        match Recording::load(&config.recording) {
+            Ok(recording) => {
+                log::info!("Playing back {}", config.recording.display());
+                inputs.release_all();
+                recorder.play(recording);
+            }
+            Err(e) => log::error!("Failed to start playback: {}", e),
+        }
+    }
+}
+
+/// Applies the keymap file over the default bindings, if there is one. The keymap only needs to
+/// list the actions it rebinds, every other action keeps its default bindings.
+fn load_keymap(config: Res<InputConfig>, mut inputs: ResMut<MappedInput>) {
+    match Keymap::load(&config.keymap) {
+        Ok(keymap) => {
+            for error in inputs.apply_keymap(&keymap) {
+                log::error!(
+                    "Ignoring keymap entry in {}: {}",
+                    config.keymap.display(),
+                    error
+                );
+            }
+        }
+        Err(e) if e.is_not_found() => {
+            log::info!(
+                "No keymap found at {}, using default bindings",
+                config.keymap.display()
+            );
+        }
+        Err(e) => log::error!("Using default bindings: {}", e),
+    }
+
+    if config.log_diagnostics {
+        log_binding_diagnostics(&inputs);
+    }
+}
+
+fn log_binding_diagnostics(inputs: &MappedInput) {
+    for diagnostic in inputs.validate() {
+        match diagnostic.severity() {
+            Severity::Info => log::info!("{}", diagnostic),
+            Severity::Warning => log::warn!("{}", diagnostic),
+        }
+    }
+}
+
+fn export_bindings(config: Res<InputConfig>, inputs: Res<MappedInput>) {
+    if !inputs.just_activated(InputDebugControls::ExportBindings) {
+        return;
+    }
+
+    let exports = [
+        (&config.graphviz, inputs.bindings_graphviz()),
+        (&config.cheat_sheet, inputs.cheat_sheet().to_markdown()),
+    ];
+
+    for (path, contents) in exports.iter() {
+        match std::fs::write(path, contents) {
+            Ok(()) => log::info!("Exported bindings to {}", path.display()),
+            Err(e) => log::error!("Failed to export bindings to {}: {}", path.display(), e),
+        }
+    }
+    // written to its own file rather than the keymap, so that the keymap only ever holds the
+    // bindings the player changed and later changes to the defaults still apply
+    match inputs.keymap().save(&config.keymap_export) {
+        Ok(()) => log::info!("Exported keymap to {}", config.keymap_export.display()),
+        Err(e) => log::error!("{}", e),
     }
 }
 
-fn debug_input(inputs: ResMut<MappedInput>) {
-    debug_binding_graph(&inputs);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::ecs::schedule::{Stage, SystemStage};
+    use bevy::input::{keyboard::KeyboardInput, mouse::MouseButtonInput};
+    use GamepadEventType::{AxisChanged, ButtonChanged, Disconnected};
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, num_derive::ToPrimitive)]
+    enum Pad {
+        Jump,
+        Look,
+        Throttle,
+    }
+
+    /// A world with just enough in it to run the input system headless
+    fn input_world() -> (World, SystemStage) {
+        let mut world = World::default();
+        world.insert_resource(Time::default());
+        world.insert_resource(InputRecorder::default());
+        world.insert_resource(Events::<KeyboardInput>::default());
+        world.insert_resource(Events::<MouseButtonInput>::default());
+        world.insert_resource(Events::<MouseMotion>::default());
+        world.insert_resource(Events::<MouseWheel>::default());
+        world.insert_resource(Events::<CursorMoved>::default());
+        world.insert_resource(Events::<CursorLeft>::default());
+        world.insert_resource(Events::<GamepadEvent>::default());
+
+        let mut inputs = MappedInput::default();
+        inputs.bind([GamepadButtonType::South], Pad::Jump);
+        inputs.bind([Stick::Left], Pad::Look);
+        inputs.bind([GamepadAxisType::LeftZ], Pad::Throttle);
+        world.insert_resource(inputs);
+
+        (world, SystemStage::single(input_handling.system()))
+    }
+
+    /// Run one frame of the input system on the given gamepad events
+    fn frame(world: &mut World, stage: &mut SystemStage, events: &[GamepadEventType]) {
+        let mut gamepad_events = world.get_resource_mut::<Events<GamepadEvent>>().unwrap();
+        for event in events {
+            gamepad_events.send(GamepadEvent(Gamepad(0), event.clone()));
+        }
+
+        stage.run(world);
+    }
+
+    fn inputs(world: &World) -> &MappedInput {
+        world.get_resource::<MappedInput>().unwrap()
+    }
+
+    #[test]
+    fn stick_dead_zone_is_rescaled() {
+        let (mut world, mut stage) = input_world();
+
+        frame(
+            &mut world,
+            &mut stage,
+            &[AxisChanged(GamepadAxisType::LeftStickX, 0.1)],
+        );
+        assert!(!inputs(&world).active(Pad::Look));
+        assert_eq!(inputs(&world).stick(Pad::Look), None);
+
+        // halfway between the default dead zone of 0.15 and full deflection
+        frame(
+            &mut world,
+            &mut stage,
+            &[AxisChanged(GamepadAxisType::LeftStickX, 0.575)],
+        );
+        assert!(inputs(&world).just_activated(Pad::Look));
+        let stick = inputs(&world).stick(Pad::Look).unwrap();
+        assert!((stick - Vec2::new(0.5, 0.0)).length() < 1e-5, "{:?}", stick);
+
+        frame(
+            &mut world,
+            &mut stage,
+            &[AxisChanged(GamepadAxisType::LeftStickX, 1.0)],
+        );
+        let stick = inputs(&world).stick(Pad::Look).unwrap();
+        assert!((stick - Vec2::new(1.0, 0.0)).length() < 1e-5, "{:?}", stick);
+    }
+
+    #[test]
+    fn stick_is_radial() {
+        let (mut world, mut stage) = input_world();
+
+        // each axis alone is inside the dead zone, but together they are outside it
+        frame(
+            &mut world,
+            &mut stage,
+            &[
+                AxisChanged(GamepadAxisType::LeftStickX, -0.12),
+                AxisChanged(GamepadAxisType::LeftStickY, -0.12),
+            ],
+        );
+        let stick = inputs(&world).stick(Pad::Look).unwrap();
+        assert!(stick.x < 0.0 && stick.y < 0.0);
+        assert!((stick.x - stick.y).abs() < 1e-5);
+    }
+
+    #[test]
+    fn stick_press_and_release() {
+        let (mut world, mut stage) = input_world();
+
+        frame(
+            &mut world,
+            &mut stage,
+            &[AxisChanged(GamepadAxisType::LeftStickY, 0.8)],
+        );
+        assert!(inputs(&world).just_activated(Pad::Look));
+
+        // staying deflected doesn't activate the action again
+        frame(
+            &mut world,
+            &mut stage,
+            &[AxisChanged(GamepadAxisType::LeftStickY, 0.6)],
+        );
+        assert!(inputs(&world).active(Pad::Look));
+        assert!(!inputs(&world).just_activated(Pad::Look));
+
+        // falling back into the dead zone releases the stick
+        frame(
+            &mut world,
+            &mut stage,
+            &[AxisChanged(GamepadAxisType::LeftStickY, 0.05)],
+        );
+        assert!(inputs(&world).just_deactivated(Pad::Look));
+        assert!(!inputs(&world).active(Pad::Look));
+    }
+
+    #[test]
+    fn axis_dead_zone_is_rescaled() {
+        let (mut world, mut stage) = input_world();
+
+        frame(
+            &mut world,
+            &mut stage,
+            &[AxisChanged(GamepadAxisType::LeftZ, 0.05)],
+        );
+        assert_eq!(inputs(&world).axis(Pad::Throttle), None);
+
+        // halfway between the default dead zone of 0.1 and full deflection
+        frame(
+            &mut world,
+            &mut stage,
+            &[AxisChanged(GamepadAxisType::LeftZ, -0.55)],
+        );
+        let throttle = inputs(&world).axis(Pad::Throttle).unwrap();
+        assert!((throttle + 0.5).abs() < 1e-5, "{}", throttle);
+    }
+
+    #[test]
+    fn button_threshold() {
+        let (mut world, mut stage) = input_world();
+
+        frame(
+            &mut world,
+            &mut stage,
+            &[ButtonChanged(GamepadButtonType::South, 0.3)],
+        );
+        assert!(!inputs(&world).active(Pad::Jump));
+
+        frame(
+            &mut world,
+            &mut stage,
+            &[ButtonChanged(GamepadButtonType::South, 0.7)],
+        );
+        assert!(inputs(&world).just_activated(Pad::Jump));
+        assert_eq!(inputs(&world).axis(Pad::Jump), Some(0.7));
+
+        frame(
+            &mut world,
+            &mut stage,
+            &[ButtonChanged(GamepadButtonType::South, 0.2)],
+        );
+        assert!(inputs(&world).just_deactivated(Pad::Jump));
+    }
+
+    #[test]
+    fn disconnect_releases_gamepad() {
+        let (mut world, mut stage) = input_world();
+
+        frame(
+            &mut world,
+            &mut stage,
+            &[
+                ButtonChanged(GamepadButtonType::South, 1.0),
+                AxisChanged(GamepadAxisType::LeftStickX, 1.0),
+            ],
+        );
+        assert!(inputs(&world).active(Pad::Jump));
+        assert!(inputs(&world).active(Pad::Look));
+
+        frame(&mut world, &mut stage, &[Disconnected]);
+        assert!(inputs(&world).just_deactivated(Pad::Jump));
+        assert!(inputs(&world).just_deactivated(Pad::Look));
+    }
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, num_derive::ToPrimitive)]
+    enum Fire {
+        Primary,
+        Secondary,
+    }
+
+    fn recording() -> Recording {
+        let trigger = Switch::from(MouseButton::Left);
+
+        Recording {
+            frames: vec![
+                RecordedFrame {
+                    time: 10.0,
+                    events: vec![
+                        InputEvent::Cursor(Vec2::new(120.0, 80.0)),
+                        InputEvent::Press(trigger),
+                    ],
+                },
+                RecordedFrame {
+                    time: 10.25,
+                    events: vec![InputEvent::Cursor(Vec2::new(130.0, 90.0))],
+                },
+                RecordedFrame {
+                    time: 10.5,
+                    events: vec![InputEvent::Release(trigger)],
+                },
+            ],
+        }
+    }
+
+    #[test]
+    fn apply_frame_drives_actions() {
+        let mut inputs = MappedInput::default();
+        inputs.bind([MouseButton::Left], Fire::Primary);
+
+        let recording = recording();
+
+        inputs.apply_frame(&recording.frames[0]);
+        assert!(inputs.just_activated(Fire::Primary));
+        assert_eq!(inputs.cursor_position(), Some(Vec2::new(120.0, 80.0)));
+
+        inputs.apply_frame(&recording.frames[1]);
+        assert!(inputs.active(Fire::Primary));
+        assert!(!inputs.just_activated(Fire::Primary));
+        assert_eq!(inputs.cursor_position(), Some(Vec2::new(130.0, 90.0)));
+        assert!((inputs.delta_seconds() - 0.25).abs() < 1e-6);
+
+        inputs.apply_frame(&recording.frames[2]);
+        assert!(inputs.just_deactivated(Fire::Primary));
+        assert!(!inputs.active(Fire::Primary));
+    }
+
+    #[test]
+    fn recording_starts_with_held_switches() {
+        let bind = |inputs: &mut MappedInput| {
+            inputs.bind([MouseButton::Left], Fire::Primary);
+            inputs.bind([Modifier::Shift], Fire::Secondary);
+        };
+
+        let mut inputs = MappedInput::default();
+        bind(&mut inputs);
+        inputs.apply_frame(&recording().frames[0]);
+        inputs.press(Switch::from(KeyCode::LShift));
+
+        let mut recorder = InputRecorder::default();
+        recorder.start_recording(&inputs);
+        let recorded = recorder.stop_recording().unwrap();
+
+        let mut replayed = MappedInput::default();
+        bind(&mut replayed);
+        replayed.apply_frame(&recorded.frames[0]);
+
+        assert!(replayed.active(Fire::Primary));
+        assert!(replayed.active(Fire::Secondary));
+        assert_eq!(replayed.cursor_position(), Some(Vec2::new(120.0, 80.0)));
+    }
+
+    #[test]
+    fn release_all_cancels_actions() {
+        let mut inputs = MappedInput::default();
+        inputs.bind([MouseButton::Left], Fire::Primary);
+
+        inputs.update(1.0);
+        inputs.press(Switch::from(MouseButton::Left));
+        inputs.update(1.1);
+        inputs.release_all();
+        assert!(inputs.just_deactivated(Fire::Primary));
+        assert!(!inputs.just_released(Fire::Primary));
+
+        inputs.update(1.2);
+        inputs.press(Switch::from(MouseButton::Left));
+        inputs.update(1.3);
+        inputs.release(Switch::from(MouseButton::Left));
+        assert!(inputs.just_released(Fire::Primary));
+    }
+
+    #[test]
+    fn playback_replaces_live_input() {
+        let (mut world, mut stage) = input_world();
+        world
+            .get_resource_mut::<MappedInput>()
+            .unwrap()
+            .bind([MouseButton::Left], Fire::Primary);
+        world
+            .get_resource_mut::<InputRecorder>()
+            .unwrap()
+            .play(recording());
+
+        // live input is ignored while the recording plays
+        frame(
+            &mut world,
+            &mut stage,
+            &[ButtonChanged(GamepadButtonType::South, 1.0)],
+        );
+        assert!(inputs(&world).just_activated(Fire::Primary));
+        assert!(!inputs(&world).active(Pad::Jump));
+        assert_eq!(
+            inputs(&world).cursor_position(),
+            Some(Vec2::new(120.0, 80.0))
+        );
+
+        // the frame time comes from the recording rather than the clock, which stands still here
+        frame(&mut world, &mut stage, &[]);
+        assert!((inputs(&world).delta_seconds() - 0.25).abs() < 1e-6);
+
+        frame(&mut world, &mut stage, &[]);
+        assert!(inputs(&world).just_deactivated(Fire::Primary));
+        assert!(world.get_resource::<InputRecorder>().unwrap().is_playing());
+
+        // once the recording runs out, live input applies again
+        frame(&mut world, &mut stage, &[]);
+        assert!(!world.get_resource::<InputRecorder>().unwrap().is_playing());
+    }
 }
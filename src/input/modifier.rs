@@ -0,0 +1,39 @@
+//! Logical modifier switches, which are held while either the left or the right physical key is
+//! held.
+//!
+//! Binding `[Modifier::Alt.into(), KeyCode::A.into()]` activates for both LAlt + A and RAlt + A.
+//! Holding both physical keys only counts once towards a combination, and the modifier is only
+//! released once both keys are released.
+use bevy::prelude::KeyCode;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Hash, PartialEq, Eq, Copy, Clone, Serialize, Deserialize)]
+pub enum Modifier {
+    Alt,
+    Ctrl,
+    Shift,
+    Super,
+}
+
+impl Modifier {
+    /// The modifier a physical key belongs to, if any
+    pub fn from_key(key: KeyCode) -> Option<Modifier> {
+        match key {
+            KeyCode::LAlt | KeyCode::RAlt => Some(Modifier::Alt),
+            KeyCode::LControl | KeyCode::RControl => Some(Modifier::Ctrl),
+            KeyCode::LShift | KeyCode::RShift => Some(Modifier::Shift),
+            KeyCode::LWin | KeyCode::RWin => Some(Modifier::Super),
+            _ => None,
+        }
+    }
+
+    /// The left and right physical keys of the modifier
+    pub fn keys(self) -> [KeyCode; 2] {
+        match self {
+            Modifier::Alt => [KeyCode::LAlt, KeyCode::RAlt],
+            Modifier::Ctrl => [KeyCode::LControl, KeyCode::RControl],
+            Modifier::Shift => [KeyCode::LShift, KeyCode::RShift],
+            Modifier::Super => [KeyCode::LWin, KeyCode::RWin],
+        }
+    }
+}
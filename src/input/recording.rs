@@ -0,0 +1,173 @@
+//! Recording and playback of the input fed into a [`MappedInput`](super::MappedInput).
+//!
+//! While recording, every event passed to the input map is stored alongside the frame it arrived
+//! in, and the recording is written to a RON file when it is stopped. During playback the
+//! recorded frames are fed into the input map one per frame, in place of the events from the
+//! keyboard, mouse and gamepad, so that a bug can be reproduced exactly as it was hit.
+//!
+//! Recordings can also be replayed without running the app by passing each frame to
+//! [`MappedInput::apply_frame`](super::MappedInput::apply_frame).
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+use super::{MappedInput, Switch};
+use crate::ron_file::{self, RonFileError};
+
+/// A single input event, as passed to the input map
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum InputEvent {
+    Press(Switch),
+    Release(Switch),
+    MouseMotion(Vec2),
+    MouseScroll(f32),
+    /// The cursor moved to a position in window coordinates
+    Cursor(Vec2),
+    CursorLeft,
+    GamepadButton(GamepadButtonType, f32),
+    GamepadAxis(GamepadAxisType, f32),
+    GamepadDisconnected,
+}
+
+/// The events received in a single frame
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RecordedFrame {
+    /// Seconds since startup at the start of the frame
+    pub time: f64,
+    pub events: Vec<InputEvent>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Recording {
+    pub frames: Vec<RecordedFrame>,
+}
+
+impl Recording {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, RonFileError> {
+        ron_file::load(path)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), RonFileError> {
+        ron_file::save(self, path)
+    }
+
+    /// Length of the recording in seconds
+    pub fn duration(&self) -> f64 {
+        match (self.frames.first(), self.frames.last()) {
+            (Some(first), Some(last)) => last.time - first.time,
+            _ => 0.0,
+        }
+    }
+}
+
+/// Actions controlling the recorder
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, num_derive::ToPrimitive)]
+pub enum RecorderControls {
+    /// Start recording, or stop and save the current recording
+    Record,
+    /// Play back the last saved recording, or stop playback
+    Play,
+}
+
+#[derive(Debug)]
+enum RecorderState {
+    Idle,
+    Recording(Recording),
+    Playing {
+        recording: Recording,
+        frame: usize,
+        /// Added to recorded times so that playback starts at the current time
+        offset: f64,
+    },
+}
+
+impl Default for RecorderState {
+    fn default() -> Self {
+        RecorderState::Idle
+    }
+}
+
+/// Resource which records the input map's events, or plays them back in place of live input
+#[derive(Debug, Default)]
+pub struct InputRecorder {
+    state: RecorderState,
+}
+
+impl InputRecorder {
+    pub fn is_recording(&self) -> bool {
+        matches!(self.state, RecorderState::Recording(_))
+    }
+
+    pub fn is_playing(&self) -> bool {
+        matches!(self.state, RecorderState::Playing { .. })
+    }
+
+    /// Start a new recording, discarding any recording in progress. The recording starts with a
+    /// snapshot of what `inputs` currently holds, so that switches held since before recording
+    /// started are held during playback too.
+    pub fn start_recording(&mut self, inputs: &MappedInput) {
+        self.state = RecorderState::Recording(Recording {
+            frames: vec![inputs.snapshot()],
+        });
+    }
+
+    /// Stop recording, returning what was recorded
+    pub fn stop_recording(&mut self) -> Option<Recording> {
+        match std::mem::take(&mut self.state) {
+            RecorderState::Recording(recording) => Some(recording),
+            state => {
+                self.state = state;
+                None
+            }
+        }
+    }
+
+    /// Play a recording from the start of the next frame. Live input is ignored until it ends.
+    pub fn play(&mut self, recording: Recording) {
+        self.state = RecorderState::Playing {
+            recording,
+            frame: 0,
+            offset: 0.0,
+        };
+    }
+
+    pub fn stop_playback(&mut self) {
+        if self.is_playing() {
+            self.state = RecorderState::Idle;
+        }
+    }
+
+    /// Store a frame of live input if recording
+    pub(super) fn record(&mut self, frame: &RecordedFrame) {
+        if let RecorderState::Recording(recording) = &mut self.state {
+            recording.frames.push(frame.clone());
+        }
+    }
+
+    /// The next frame of the recording being played back, retimed relative to `now` for the
+    /// first frame. Playback stops once the recording has been exhausted.
+    pub(super) fn next_frame(&mut self, now: f64) -> Option<RecordedFrame> {
+        if let RecorderState::Playing {
+            recording,
+            frame,
+            offset,
+        } = &mut self.state
+        {
+            if let Some(next) = recording.frames.get(*frame) {
+                if *frame == 0 {
+                    *offset = now - next.time;
+                }
+                *frame += 1;
+
+                return Some(RecordedFrame {
+                    time: next.time + *offset,
+                    events: next.events.clone(),
+                });
+            }
+
+            self.state = RecorderState::Idle;
+        }
+
+        None
+    }
+}
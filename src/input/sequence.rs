@@ -0,0 +1,33 @@
+//! Key sequences activate an action when a series of combinations is pressed in order, such as
+//! `Q` followed by `W`.
+//!
+//! Each step of a sequence is bound into the binding graph like any other combination, but with
+//! a step edge in place of an action edge. Pressing the combination for the next expected step
+//! advances the sequence, while pressing any other key, or waiting longer than the timeout
+//! between steps, abandons it. The action is activated by the final step, and stays active until
+//! that step's terminator is released.
+use super::{inputmap::ActionId, Switch};
+
+pub(super) type SequenceId = usize;
+
+#[derive(Debug, Clone)]
+pub(super) struct Sequence {
+    pub action: ActionId,
+    pub steps: Vec<Vec<Switch>>,
+    /// Maximum time in seconds between consecutive steps
+    pub timeout: f32,
+    /// The number of steps matched so far
+    pub progress: usize,
+    pub deadline: f64,
+}
+
+/// A sequence which has been started but not yet completed, for showing the player which keys
+/// are expected next
+#[derive(Debug, Clone)]
+pub struct SequenceProgress<'a> {
+    pub action: ActionId,
+    pub matched: &'a [Vec<Switch>],
+    pub remaining: &'a [Vec<Switch>],
+    /// Seconds left to press the next step before the sequence is abandoned
+    pub time_left: f32,
+}
@@ -10,6 +10,7 @@ mod materials;
 mod physics;
 //mod selection;
 mod player;
+mod ron_file;
 mod skysphere;
 mod units;
 
@@ -32,6 +33,10 @@ use units::ship::{spawn_ship, spawn_station};
 enum SystemLabels {
     Input,
     Camera,
+    /// Selection setup during startup, and applying clicks on units every frame
+    Selection,
+    /// Casting the mouse ray from the cursor position in the input map
+    MouseRay,
 }
 
 fn main() {
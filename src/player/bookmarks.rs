@@ -0,0 +1,131 @@
+//! # Camera bookmarks
+//! Numbered camera views which the player can save and return to. Holding Ctrl and pressing
+//! one of F1 to F4 saves the current view, pressing the key alone moves the camera back to it.
+//! Bookmarks are kept in a RON file so they survive restarts.
+use std::{collections::BTreeMap, path::Path};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    input::{MappedInput, Modifier, Switch},
+    player::camera::{CameraController, CameraFollow, CAMERA_CONTEXT},
+    ron_file::{self, RonFileError},
+    SystemLabels,
+};
+
+pub struct BookmarkPlugin;
+
+impl Plugin for BookmarkPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_startup_system(setup.system()).add_system(
+            bookmark_controls
+                .system()
+                .after(SystemLabels::Input)
+                .before(SystemLabels::Camera),
+        );
+    }
+}
+
+/// File the bookmarks are saved to
+pub const BOOKMARKS_PATH: &str = "bookmarks.ron";
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CameraBookmark {
+    pub focus: Vec3,
+    pub radius: f32,
+    pub rotation: Quat,
+}
+
+/// Saved views by slot number, starting from 1
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CameraBookmarks {
+    pub slots: BTreeMap<u8, CameraBookmark>,
+}
+
+impl CameraBookmarks {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, RonFileError> {
+        ron_file::load(path)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), RonFileError> {
+        ron_file::save(self, path)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, num_derive::ToPrimitive)]
+pub enum Bookmarks {
+    Save1,
+    Save2,
+    Save3,
+    Save4,
+    Recall1,
+    Recall2,
+    Recall3,
+    Recall4,
+}
+
+/// Key, save action and recall action of each bookmark slot
+const SLOTS: [(KeyCode, Bookmarks, Bookmarks); 4] = [
+    (KeyCode::F1, Bookmarks::Save1, Bookmarks::Recall1),
+    (KeyCode::F2, Bookmarks::Save2, Bookmarks::Recall2),
+    (KeyCode::F3, Bookmarks::Save3, Bookmarks::Recall3),
+    (KeyCode::F4, Bookmarks::Save4, Bookmarks::Recall4),
+];
+
+fn setup(mut commands: Commands, mut inputs: ResMut<MappedInput>) {
+    for &(key, save, recall) in SLOTS.iter() {
+        inputs.bind([Switch::from(Modifier::Ctrl), key.into()], save);
+        inputs.bind([key], recall);
+    }
+
+    inputs.assign_context::<Bookmarks>(CAMERA_CONTEXT);
+
+    let bookmarks = match CameraBookmarks::load(BOOKMARKS_PATH) {
+        Ok(bookmarks) => bookmarks,
+        Err(e) if e.is_not_found() => CameraBookmarks::default(),
+        Err(e) => {
+            log::error!("Discarding camera bookmarks: {}", e);
+            CameraBookmarks::default()
+        }
+    };
+
+    commands.insert_resource(bookmarks);
+}
+
+fn bookmark_controls(
+    mut commands: Commands,
+    inputs: Res<MappedInput>,
+    mut bookmarks: ResMut<CameraBookmarks>,
+    mut q: Query<(Entity, &mut CameraController)>,
+) {
+    for (slot, &(_, save, recall)) in SLOTS.iter().enumerate() {
+        let slot = slot as u8 + 1;
+
+        for (entity, mut controller) in q.iter_mut() {
+            if inputs.just_activated(save) {
+                bookmarks.slots.insert(
+                    slot,
+                    CameraBookmark {
+                        focus: controller.target_focus,
+                        radius: controller.target_radius,
+                        rotation: controller.target_rotation,
+                    },
+                );
+                log::info!("Saved camera bookmark {}", slot);
+
+                if let Err(e) = bookmarks.save(BOOKMARKS_PATH) {
+                    log::error!("{}", e);
+                }
+            } else if inputs.just_activated(recall) {
+                if let Some(&bookmark) = bookmarks.slots.get(&slot) {
+                    // the camera's damping carries it smoothly to the bookmarked view
+                    commands.entity(entity).remove::<CameraFollow>();
+                    controller.target_focus = bookmark.focus;
+                    controller.target_radius = bookmark.radius;
+                    controller.target_rotation = bookmark.rotation;
+                }
+            }
+        }
+    }
+}
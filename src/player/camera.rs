@@ -1,25 +1,55 @@
-use std::f32::consts::PI;
+use std::f32::consts::FRAC_PI_2;
 
 use bevy::{
-    input::mouse::MouseButton, prelude::*, render::camera::Camera,
-    render::camera::PerspectiveProjection,
+    input::mouse::MouseButton,
+    prelude::*,
+    render::camera::{
+        Camera, CameraProjection, DepthCalculation, OrthographicProjection, PerspectiveProjection,
+        ScalingMode,
+    },
 };
 use bevy_inspector_egui::{Inspectable, InspectableRegistry};
+use bevy_mod_picking::PickableMesh;
+use bevy_rapier3d::prelude::*;
 
-use crate::SystemLabels;
+use crate::{
+    input::{Gesture, Modifier, Stick},
+    SystemLabels,
+};
 //use log::debug;
 pub struct CameraControlPlugin;
 
 impl Plugin for CameraControlPlugin {
     fn build(&self, app: &mut AppBuilder) {
-        app.add_startup_system(setup.system())
+        app.insert_resource(CameraSettings::default())
+            // the camera context is at the bottom of the context stack
+            .add_startup_system(setup.system().label(SystemLabels::Camera))
             .add_system(
                 camera_movement
                     .system()
                     .label(SystemLabels::Camera)
                     .after(SystemLabels::Input),
             )
-            .add_system(mouseray_system.system().after(SystemLabels::Input));
+            .add_system(
+                camera_follow
+                    .system()
+                    .after(SystemLabels::Input)
+                    .before(SystemLabels::Camera),
+            )
+            .add_system(tactical_view.system().after(SystemLabels::Camera))
+            .add_system(
+                double_click_focus
+                    .system()
+                    .after(SystemLabels::Input)
+                    .before(SystemLabels::Camera),
+            )
+            .add_system(
+                mouseray_system
+                    .system()
+                    .label(SystemLabels::MouseRay)
+                    .after(SystemLabels::Input)
+                    .before(SystemLabels::Camera),
+            );
 
         let mut registry = app
             .world_mut()
@@ -29,11 +59,30 @@ impl Plugin for CameraControlPlugin {
         registry.register::<CameraController>();
     }
 }
+/// Orbit camera state. Input moves the `target_` values, and the current focus, radius and
+/// orientation (the camera transform's rotation) follow them with critically damped motion.
 #[derive(Inspectable)]
 pub struct CameraController {
     _sensitivity: f32,
     pub radius: f32,
     pub focus: Vec3,
+    pub target_radius: f32,
+    pub target_focus: Vec3,
+    pub target_rotation: Quat,
+    /// How quickly the focus catches up with its target. Zero or less snaps instantly.
+    #[inspectable(min = 0.0, max = 50.0)]
+    pub focus_stiffness: f32,
+    /// How quickly the radius catches up with its target. Zero or less snaps instantly.
+    #[inspectable(min = 0.0, max = 50.0)]
+    pub zoom_stiffness: f32,
+    /// How quickly the orientation catches up with its target. Zero or less snaps instantly.
+    #[inspectable(min = 0.0, max = 50.0)]
+    pub rotation_stiffness: f32,
+    /// Keyboard and gamepad panning speed, in multiples of the radius per second
+    #[inspectable(min = 0.0, max = 5.0)]
+    pub pan_speed: f32,
+    /// Zooming with the mouse moves the focus towards or away from the cursor
+    pub zoom_to_cursor: bool,
     upside_down: bool,
 }
 
@@ -42,12 +91,77 @@ impl Default for CameraController {
         CameraController {
             focus: Vec3::ZERO,
             radius: 40f32,
+            target_focus: Vec3::ZERO,
+            target_radius: 40f32,
+            target_rotation: Quat::IDENTITY,
+            focus_stiffness: 12.0,
+            zoom_stiffness: 10.0,
+            rotation_stiffness: 16.0,
+            pan_speed: 1.0,
+            zoom_to_cursor: true,
             _sensitivity: 10f32,
             upside_down: false,
         }
     }
 }
 
+impl CameraController {
+    /// A controller orbiting `focus` from the current position and orientation of `transform`
+    pub fn from_transform(transform: &Transform, focus: Vec3) -> Self {
+        let radius = transform.translation.distance(focus);
+
+        CameraController {
+            focus,
+            radius,
+            target_focus: focus,
+            target_radius: radius,
+            target_rotation: transform.rotation,
+            ..Default::default()
+        }
+    }
+}
+
+/// Limits and screen edge panning shared by every camera
+#[derive(Debug, Clone)]
+pub struct CameraSettings {
+    /// Pan when the cursor is near the edge of the window
+    pub edge_pan: bool,
+    /// Distance from the window edge within which edge panning starts, in pixels
+    pub edge_margin: f32,
+    /// Edge panning speed, in multiples of the radius per second
+    pub edge_speed: f32,
+    /// Corners of the playable volume, which the focus is kept inside
+    pub bounds_min: Vec3,
+    pub bounds_max: Vec3,
+    pub min_radius: f32,
+    pub max_radius: f32,
+}
+
+impl Default for CameraSettings {
+    fn default() -> Self {
+        CameraSettings {
+            edge_pan: true,
+            edge_margin: 8.0,
+            edge_speed: 1.0,
+            bounds_min: Vec3::new(-500.0, -100.0, -500.0),
+            bounds_max: Vec3::new(500.0, 100.0, 500.0),
+            min_radius: 10.0,
+            max_radius: 500.0,
+        }
+    }
+}
+
+/// Velocities of the damped camera motion
+#[derive(Debug, Default)]
+struct CameraVelocity {
+    focus: Vec3,
+    radius: f32,
+    rotation: Vec4,
+}
+
+/// Input context of the camera controls
+pub const CAMERA_CONTEXT: &str = "Camera";
+
 #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, num_derive::ToPrimitive)]
 pub enum Pan {
     Left,
@@ -63,8 +177,51 @@ pub enum Controls {
     Orbit,
     Pan,
     Zoom,
+    /// Recenter the focus on the unit or point under the cursor
+    Focus,
+    /// Switch between the perspective view and the top-down orthographic tactical view
+    TacticalView,
+    /// Raise or lower the control cursor above the control plane with the mouse
+    Altitude,
 }
 
+/// Present on the camera while it is in the top-down orthographic tactical view
+pub struct TacticalView {
+    /// Projection and orientation restored on leaving the tactical view
+    perspective: PerspectiveProjection,
+    rotation: Quat,
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, num_derive::ToPrimitive)]
+pub enum GamepadControls {
+    Orbit,
+    Pan,
+    ZoomIn,
+    ZoomOut,
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, num_derive::ToPrimitive)]
+pub enum Follow {
+    /// Start following the selected units, or the unit itself if only one is selected, or stop
+    /// following
+    Toggle,
+}
+
+/// While present on the camera, the camera focus tracks a moving target. Orbiting and zooming
+/// stay relative to the moving focus, while panning disengages the follow.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CameraFollow {
+    /// The centroid of the selected units
+    Selection,
+    /// A single unit, which stays followed when the selection changes
+    Entity(Entity),
+}
+
+/// Orbit speed at full stick deflection, in the same units as mouse motion (pixels per second)
+const GAMEPAD_ORBIT_RATE: f32 = 800.0;
+/// Zoom speed at full trigger, in the same units as mouse scroll (lines per second)
+const GAMEPAD_ZOOM_RATE: f32 = 30.0;
+
 // Tags an entity as capable of panning and orbiting.
 fn get_primary_window_size(windows: &Res<Windows>) -> Vec2 {
     let window = windows.get_primary().unwrap();
@@ -74,12 +231,15 @@ fn get_primary_window_size(windows: &Res<Windows>) -> Vec2 {
 
 fn setup(mut commands: Commands, mut inputmap: ResMut<crate::input::MappedInput>) {
     // spawn player camera
+    let transform = Transform::from_xyz(0.0, 2.5, 10.0).looking_at(Vec3::ZERO, Vec3::Y);
+
     commands
         .spawn_bundle(PerspectiveCameraBundle {
-            transform: Transform::from_xyz(0.0, 2.5, 10.0).looking_at(Vec3::ZERO, Vec3::Y),
+            transform,
             ..Default::default()
         })
-        .insert(CameraController::default())
+        .insert(CameraController::from_transform(&transform, Vec3::ZERO))
+        .insert(CameraVelocity::default())
         .insert(Option::<MouseRay>::default())
         .insert(Option::<ControlCursor>::default());
 
@@ -87,8 +247,10 @@ fn setup(mut commands: Commands, mut inputmap: ResMut<crate::input::MappedInput>
     inputmap.bind([KeyCode::D], Pan::Right);
     inputmap.bind([KeyCode::W], Pan::Forward);
     inputmap.bind([KeyCode::S], Pan::Backward);
-    inputmap.bind([KeyCode::LShift], Pan::Up);
-    inputmap.bind([KeyCode::LControl], Pan::Down);
+    // kept off the modifiers, which would otherwise move the camera whenever they are held to
+    // save a bookmark, queue orders or add to the selection
+    inputmap.bind([KeyCode::E], Pan::Up);
+    inputmap.bind([KeyCode::Q], Pan::Down);
 
     inputmap.bind(
         [
@@ -106,16 +268,52 @@ fn setup(mut commands: Commands, mut inputmap: ResMut<crate::input::MappedInput>
     );
 
     inputmap.bind([crate::input::Switch::MouseScroll], Controls::Zoom);
+    // the middle button, as a left double click selects all units of a type
+    inputmap.bind_gesture(
+        [MouseButton::Middle],
+        Controls::Focus,
+        Gesture::DoubleTap { window: 0.3 },
+    );
+    inputmap.bind([KeyCode::Tab], Controls::TacticalView);
+    inputmap.bind(
+        [
+            crate::input::Switch::from(Modifier::Alt),
+            crate::input::Switch::MouseMotion,
+        ],
+        Controls::Altitude,
+    );
+
+    inputmap.bind([Stick::Right], GamepadControls::Orbit);
+    inputmap.bind([Stick::Left], GamepadControls::Pan);
+    inputmap.bind([GamepadButtonType::RightTrigger2], GamepadControls::ZoomIn);
+    inputmap.bind([GamepadButtonType::LeftTrigger2], GamepadControls::ZoomOut);
+
+    inputmap.bind([KeyCode::F], Follow::Toggle);
+
+    inputmap.assign_context::<Pan>(CAMERA_CONTEXT);
+    inputmap.assign_context::<Controls>(CAMERA_CONTEXT);
+    inputmap.assign_context::<GamepadControls>(CAMERA_CONTEXT);
+    inputmap.assign_context::<Follow>(CAMERA_CONTEXT);
+    inputmap.push_context(CAMERA_CONTEXT, false);
 }
 
 fn camera_movement(
-    time: Res<Time>,
+    mut commands: Commands,
     windows: Res<Windows>,
+    settings: Res<CameraSettings>,
     input: Res<crate::input::MappedInput>,
+    mut focus_events: EventReader<bevy::window::WindowFocused>,
+    // not known until the first focus event, in which case the window is assumed focused
+    mut window_focused: Local<Option<bool>>,
     mut q: Query<(
+        Entity,
         &mut CameraController,
+        &mut CameraVelocity,
         &mut Transform,
-        &PerspectiveProjection,
+        Option<&PerspectiveProjection>,
+        Option<&TacticalView>,
+        &Option<ControlCursor>,
+        Option<&CameraFollow>,
     )>,
 ) {
     // change input mapping for orbit and panning here
@@ -138,13 +336,28 @@ fn camera_movement(
     }
 
     let mut pan = input.motion(Controls::Pan).unwrap_or(Vec2::ZERO);
-    let scroll = input.scroll(Controls::Zoom).unwrap_or(0.0);
+    let mouse_scroll = input.scroll(Controls::Zoom).unwrap_or(0.0);
+    let mut scroll = mouse_scroll;
 
-    if input.just_deactivated(Controls::Orbit) || input.just_activated(Controls::Orbit) {
-        orbit_button_changed = true;
+    // input time rather than `Time`, so that played back recordings move the camera the same way
+    let dt = input.delta_seconds();
+
+    if let Some(stick) = input.stick(GamepadControls::Orbit) {
+        // stick y points up, whereas mouse motion y points down the screen
+        rotation_move += Vec2::new(stick.x, -stick.y) * GAMEPAD_ORBIT_RATE * dt;
     }
 
-    let _rot_rate = PI * time.delta().as_secs_f32();
+    let zoom = input.axis(GamepadControls::ZoomIn).unwrap_or(0.0)
+        - input.axis(GamepadControls::ZoomOut).unwrap_or(0.0);
+    scroll += zoom * GAMEPAD_ZOOM_RATE * dt;
+
+    if input.just_deactivated(Controls::Orbit)
+        || input.just_activated(Controls::Orbit)
+        || input.just_deactivated(GamepadControls::Orbit)
+        || input.just_activated(GamepadControls::Orbit)
+    {
+        orbit_button_changed = true;
+    }
 
     let mut translation = Vec3::ZERO;
 
@@ -172,17 +385,58 @@ fn camera_movement(
         translation -= Vec3::Y;
     }
 
-    for (mut pan_orbit, mut transform, projection) in q.iter_mut() {
+    if let Some(stick) = input.stick(GamepadControls::Pan) {
+        translation += Vec3::new(stick.x, 0.0, -stick.y);
+    }
+
+    for event in focus_events.iter() {
+        *window_focused = Some(event.focused);
+    }
+
+    let mut edge = Vec3::ZERO;
+
+    if settings.edge_pan && window_focused.unwrap_or(true) {
+        if let Some(cursor) = input.cursor_position() {
+            let window = get_primary_window_size(&windows);
+            let margin = settings.edge_margin;
+
+            // window coordinates start at the bottom left
+            if cursor.x < margin {
+                edge -= Vec3::X;
+            } else if cursor.x > window.x - margin {
+                edge += Vec3::X;
+            }
+
+            if cursor.y < margin {
+                edge += Vec3::Z;
+            } else if cursor.y > window.y - margin {
+                edge -= Vec3::Z;
+            }
+        }
+    }
+
+    for (
+        entity,
+        mut pan_orbit,
+        mut velocity,
+        mut transform,
+        perspective,
+        tactical,
+        cursor,
+        follow,
+    ) in q.iter_mut()
+    {
+        // the tactical view is scaled to match the perspective view it replaced
+        let projection = perspective.or_else(|| tactical.map(|t| &t.perspective));
+
         if orbit_button_changed {
             // only check for upside down when orbiting started or ended this frame
             // if the camera is "upside" down, panning horizontally would be inverted, so invert the input to make it correct
-            let up = transform.rotation * Vec3::Y;
+            let up = pan_orbit.target_rotation * Vec3::Y;
             pan_orbit.upside_down = up.y <= 0.0;
         }
 
-        let mut any = false;
         if rotation_move.length_squared() > 0.0 {
-            any = true;
             let window = get_primary_window_size(&windows);
             let delta_x = {
                 let delta = rotation_move.x / window.x * std::f32::consts::PI * 2.0;
@@ -194,44 +448,341 @@ fn camera_movement(
             };
             let delta_y = rotation_move.y / window.y * std::f32::consts::PI;
             let yaw = Quat::from_rotation_y(-delta_x);
-            let pitch = Quat::from_rotation_x(-delta_y);
-            transform.rotation = yaw * transform.rotation; // rotate around global y axis
-            transform.rotation = transform.rotation * pitch; // rotate around local x axis
+            let pitch = match tactical {
+                // the tactical view always looks straight down
+                Some(_) => Quat::IDENTITY,
+                None => Quat::from_rotation_x(-delta_y),
+            };
+            // rotate around global y axis, then around local x axis
+            pan_orbit.target_rotation = yaw * pan_orbit.target_rotation * pitch;
         } else if pan.length_squared() > 0.0 {
-            any = true;
             // make panning distance independent of resolution and FOV,
             let window = get_primary_window_size(&windows);
-            pan *= Vec2::new(projection.fov * projection.aspect_ratio, projection.fov) / window;
+            if let Some(projection) = projection {
+                pan *= Vec2::new(projection.fov * projection.aspect_ratio, projection.fov) / window;
+            }
             // translate by local axes
-            let right = transform.rotation * Vec3::X * -pan.x;
-            let up = transform.rotation * Vec3::Y * pan.y;
+            let right = pan_orbit.target_rotation * Vec3::X * -pan.x;
+            let up = pan_orbit.target_rotation * Vec3::Y * pan.y;
             // make panning proportional to distance away from focus point
-            let translation = (right + up) * pan_orbit.radius;
-            pan_orbit.focus += translation;
+            let translation = (right + up) * pan_orbit.target_radius;
+            pan_orbit.target_focus += translation;
         } else if scroll.abs() > 0.0 {
-            any = true;
-            pan_orbit.radius -= scroll * pan_orbit.radius * 0.02;
+            let old_radius = pan_orbit.target_radius;
+            pan_orbit.target_radius -= scroll * pan_orbit.target_radius * 0.02;
             // dont allow zoom to reach zero or you get stuck
-            pan_orbit.radius = f32::clamp(pan_orbit.radius, 10.00, 500.00);
-        } else if translation.length_squared() > 0.0 {
-            any = true;
-
-            let local_z = transform.local_z();
+            pan_orbit.target_radius = f32::clamp(
+                pan_orbit.target_radius,
+                settings.min_radius,
+                settings.max_radius,
+            );
+
+            match cursor {
+                Some(cursor) if pan_orbit.zoom_to_cursor && mouse_scroll != 0.0 => {
+                    // move the focus by the same fraction as the radius, which keeps the point
+                    // under the cursor roughly fixed on screen
+                    let fraction = 1.0 - pan_orbit.target_radius / old_radius;
+                    let offset = cursor.pos - pan_orbit.target_focus;
+                    pan_orbit.target_focus += offset * fraction;
+                }
+                _ => {}
+            }
+        } else if translation.length_squared() > 0.0 || edge.length_squared() > 0.0 {
+            let local_z = pan_orbit.target_rotation * Vec3::Z;
             let theta = f32::atan2(local_z.x, local_z.z);
             let world_z = Quat::from_rotation_y(theta);
 
-            //let right = transform.rotation * Vec3::X * translation.x;
-            //let forward = transform.rotation * Vec3::Z * translation.z;
-            pan_orbit.focus += world_z * translation; //(right + forward) + pan_orbit.focus;
+            // pan at a fixed fraction of the radius per second, so the apparent speed doesn't
+            // depend on the frame rate or zoom level
+            let movement = translation * pan_orbit.pan_speed + edge * settings.edge_speed;
+            pan_orbit.target_focus += world_z * movement * pan_orbit.target_radius * dt;
+
+            // edge panning depends on the window and cursor rather than an action, so unlike the
+            // other ways of panning it is only known here, and disengages the follow here
+            if follow.is_some() && edge.length_squared() > 0.0 {
+                commands.entity(entity).remove::<CameraFollow>();
+            }
+        }
+
+        pan_orbit.target_focus = pan_orbit
+            .target_focus
+            .max(settings.bounds_min)
+            .min(settings.bounds_max);
+
+        // move the current state towards the target
+        pan_orbit.focus = damp_vec3(
+            pan_orbit.focus,
+            pan_orbit.target_focus,
+            &mut velocity.focus,
+            pan_orbit.focus_stiffness,
+            dt,
+        );
+        pan_orbit.radius = damp_f32(
+            pan_orbit.radius,
+            pan_orbit.target_radius,
+            &mut velocity.radius,
+            pan_orbit.zoom_stiffness,
+            dt,
+        );
+        transform.rotation = damp_quat(
+            transform.rotation,
+            pan_orbit.target_rotation,
+            &mut velocity.rotation,
+            pan_orbit.rotation_stiffness,
+            dt,
+        );
+
+        // emulating parent/child to make the yaw/y-axis rotation behave like a turntable
+        // parent = x and y rotation
+        // child = z-offset
+        let rot_matrix = Mat3::from_quat(transform.rotation);
+        transform.translation =
+            pan_orbit.focus + rot_matrix.mul_vec3(Vec3::new(0.0, 0.0, pan_orbit.radius));
+    }
+}
+
+/// Critically damped spring from `current` towards `target`, with angular frequency `stiffness`.
+/// Unlike exponential smoothing, this starts moving smoothly when the target changes, and never
+/// overshoots. A stiffness of zero or less snaps to the target.
+fn damp_f32(current: f32, target: f32, velocity: &mut f32, stiffness: f32, dt: f32) -> f32 {
+    if stiffness <= 0.0 {
+        *velocity = 0.0;
+        return target;
+    }
+
+    let offset = current - target;
+    let decay = (-stiffness * dt).exp();
+    let temp = (*velocity + stiffness * offset) * dt;
+    *velocity = (*velocity - stiffness * temp) * decay;
+
+    target + (offset + temp) * decay
+}
+
+fn damp_vec3(current: Vec3, target: Vec3, velocity: &mut Vec3, stiffness: f32, dt: f32) -> Vec3 {
+    if stiffness <= 0.0 {
+        *velocity = Vec3::ZERO;
+        return target;
+    }
+
+    let offset = current - target;
+    let decay = (-stiffness * dt).exp();
+    let temp = (*velocity + stiffness * offset) * dt;
+    *velocity = (*velocity - stiffness * temp) * decay;
+
+    target + (offset + temp) * decay
+}
+
+/// Damps the quaternion components as a 4d vector, which is close enough to a spring on the
+/// rotation angle for the small steps taken each frame
+fn damp_quat(current: Quat, target: Quat, velocity: &mut Vec4, stiffness: f32, dt: f32) -> Quat {
+    if stiffness <= 0.0 {
+        *velocity = Vec4::ZERO;
+        return target;
+    }
+
+    let current = Vec4::from(current);
+    let mut target = Vec4::from(target);
+
+    // q and -q are the same rotation, so take the shorter way around
+    if current.dot(target) < 0.0 {
+        target = -target;
+    }
+
+    let offset = current - target;
+    let decay = (-stiffness * dt).exp();
+    let temp = (*velocity + stiffness * offset) * dt;
+    *velocity = (*velocity - stiffness * temp) * decay;
+
+    let damped = target + (offset + temp) * decay;
+    Quat::from_xyzw(damped.x, damped.y, damped.z, damped.w).normalize()
+}
+
+fn camera_follow(
+    mut commands: Commands,
+    input: Res<crate::input::MappedInput>,
+    mut q: Query<(Entity, &mut CameraController, Option<&CameraFollow>)>,
+    selected: Query<(Entity, &GlobalTransform), With<crate::units::Selected>>,
+    targets: Query<&GlobalTransform>,
+) {
+    let panning = input.active(Controls::Pan)
+        || input.stick(GamepadControls::Pan).is_some()
+        || [
+            Pan::Left,
+            Pan::Right,
+            Pan::Forward,
+            Pan::Backward,
+            Pan::Up,
+            Pan::Down,
+        ]
+        .iter()
+        .any(|&pan| input.active(pan));
+
+    for (entity, mut controller, follow) in q.iter_mut() {
+        let follow = match follow {
+            Some(_) if panning || input.just_activated(Follow::Toggle) => {
+                commands.entity(entity).remove::<CameraFollow>();
+                continue;
+            }
+            Some(&follow) => follow,
+            None if input.just_activated(Follow::Toggle) => {
+                let mut units = selected.iter().map(|(unit, _)| unit);
+                let follow = match (units.next(), units.next()) {
+                    (Some(unit), None) => CameraFollow::Entity(unit),
+                    _ => CameraFollow::Selection,
+                };
+
+                commands.entity(entity).insert(follow);
+                follow
+            }
+            None => continue,
+        };
+
+        let target = match follow {
+            CameraFollow::Selection => {
+                let mut count = 0;
+                let sum = selected.iter().fold(Vec3::ZERO, |sum, (_, transform)| {
+                    count += 1;
+                    sum + transform.translation
+                });
+
+                if count > 0 {
+                    Some(sum / count as f32)
+                } else {
+                    None
+                }
+            }
+            CameraFollow::Entity(target) => targets.get(target).ok().map(|t| t.translation),
+        };
+
+        match target {
+            Some(target) => controller.target_focus = target,
+            // nothing left to follow
+            None => {
+                commands.entity(entity).remove::<CameraFollow>();
+            }
+        }
+    }
+}
+
+/// Switches the camera between perspective and the orthographic tactical view, and keeps the
+/// tactical view's scale in step with the camera radius
+fn tactical_view(
+    mut commands: Commands,
+    input: Res<crate::input::MappedInput>,
+    windows: Res<Windows>,
+    mut q: Query<(
+        Entity,
+        &mut Camera,
+        &mut CameraController,
+        Option<&PerspectiveProjection>,
+        Option<&mut OrthographicProjection>,
+        Option<&TacticalView>,
+    )>,
+) {
+    let toggle = input.just_activated(Controls::TacticalView);
+
+    for (entity, mut camera, mut controller, perspective, orthographic, tactical) in q.iter_mut() {
+        let window = match windows.get(camera.window) {
+            Some(window) => window,
+            None => continue,
+        };
+
+        match (perspective, orthographic, tactical) {
+            (Some(perspective), _, None) if toggle => {
+                let mut orthographic = OrthographicProjection {
+                    scaling_mode: ScalingMode::FixedVertical,
+                    scale: tactical_scale(controller.radius, perspective.fov),
+                    near: perspective.near,
+                    far: perspective.far,
+                    depth_calculation: DepthCalculation::Distance,
+                    ..Default::default()
+                };
+                apply_projection(&mut camera, &mut orthographic, window);
+
+                commands
+                    .entity(entity)
+                    .remove::<PerspectiveProjection>()
+                    .insert(orthographic)
+                    .insert(TacticalView {
+                        perspective: perspective.clone(),
+                        rotation: controller.target_rotation,
+                    });
+
+                // keep the heading, but look straight down
+                let local_z = controller.target_rotation * Vec3::Z;
+                let heading = f32::atan2(local_z.x, local_z.z);
+                controller.target_rotation =
+                    Quat::from_rotation_y(heading) * Quat::from_rotation_x(-FRAC_PI_2);
+            }
+            (None, Some(_), Some(tactical)) if toggle => {
+                let mut perspective = tactical.perspective.clone();
+                apply_projection(&mut camera, &mut perspective, window);
+
+                controller.target_rotation = tactical.rotation;
+
+                commands
+                    .entity(entity)
+                    .remove::<OrthographicProjection>()
+                    .remove::<TacticalView>()
+                    .insert(perspective);
+            }
+            (None, Some(mut orthographic), Some(tactical)) => {
+                // bevy only updates projections when the window is resized, so zooming has to
+                // update the camera's projection matrix itself
+                let scale = tactical_scale(controller.radius, tactical.perspective.fov);
+
+                if orthographic.scale != scale {
+                    orthographic.scale = scale;
+                    apply_projection(&mut camera, &mut *orthographic, window);
+                }
+            }
+            _ => {}
         }
+    }
+}
+
+/// Orthographic scale which shows the same area around the focus as a perspective projection
+fn tactical_scale(radius: f32, fov: f32) -> f32 {
+    radius * (fov / 2.0).tan()
+}
+
+fn apply_projection(camera: &mut Camera, projection: &mut impl CameraProjection, window: &Window) {
+    projection.update(window.width(), window.height());
+    camera.projection_matrix = projection.get_projection_matrix();
+    camera.depth_calculation = projection.depth_calculation();
+}
+
+/// Recenters the camera on the unit under the cursor when double clicked with the middle button,
+/// or on the point of the control plane under the cursor if there is no unit there
+fn double_click_focus(
+    mut commands: Commands,
+    input: Res<crate::input::MappedInput>,
+    query_pipeline: Res<QueryPipeline>,
+    collider_query: QueryPipelineColliderComponentsQuery,
+    units: Query<&GlobalTransform, With<PickableMesh>>,
+    mut q: Query<(
+        Entity,
+        &mut CameraController,
+        &Option<MouseRay>,
+        &Option<ControlCursor>,
+    )>,
+) {
+    if !input.just_activated(Controls::Focus) {
+        return;
+    }
+
+    for (entity, mut controller, mouseray, cursor) in q.iter_mut() {
+        let unit = mouseray
+            .as_ref()
+            .and_then(|ray| ray.cast(&query_pipeline, &collider_query))
+            .and_then(|unit| units.get(unit).ok())
+            .map(|transform| transform.translation);
+
+        let focus = unit.or_else(|| cursor.as_ref().map(|cursor| cursor.pos));
 
-        if any {
-            // emulating parent/child to make the yaw/y-axis rotation behave like a turntable
-            // parent = x and y rotation
-            // child = z-offset
-            let rot_matrix = Mat3::from_quat(transform.rotation);
-            transform.translation =
-                pan_orbit.focus + rot_matrix.mul_vec3(Vec3::new(0.0, 0.0, pan_orbit.radius));
+        if let Some(focus) = focus {
+            commands.entity(entity).remove::<CameraFollow>();
+            controller.target_focus = focus;
         }
     }
 }
@@ -242,9 +793,18 @@ fn camera_movement(
 /// ray with the control plane
 #[derive(Default)]
 pub struct ControlCursor {
+    /// The cursor position raised or lowered to the chosen height
     pub pos: Vec3,
+    /// Intersection of the mouse ray with the control plane
+    pub plane: Vec3,
+    /// Height of `pos` above the control plane, set with [`Controls::Altitude`]
+    pub height: f32,
 }
 
+/// Rate at which vertical mouse motion changes the control cursor height, as a fraction of the
+/// camera radius per pixel
+const ALTITUDE_RATE: f32 = 0.005;
+
 #[derive(Debug, Default, Copy, Clone)]
 pub struct MouseRay {
     pub near: Vec3,
@@ -252,8 +812,59 @@ pub struct MouseRay {
     pub direction: Vec3,
 }
 
+impl MouseRay {
+    /// The ray through a point in window coordinates, from the near plane to the far plane.
+    ///
+    /// The near and far points are found by unprojecting normalized device coordinates, which
+    /// is more robust than using the location of the camera as the start of the ray, because
+    /// ortho cameras have a focal point at infinity!
+    pub fn from_screen(
+        camera: &Camera,
+        camera_transform: &GlobalTransform,
+        window_size: Vec2,
+        position: Vec2,
+    ) -> Self {
+        // Normalized device coordinates run from (-1, -1, 0) to (1, 1, 1)
+        let ndc = (position / window_size) * 2.0 - Vec2::ONE;
+
+        let ndc_to_world: Mat4 =
+            camera_transform.compute_matrix() * camera.projection_matrix.inverse();
+        let near = ndc_to_world.project_point3(ndc.extend(0.0));
+        let far = ndc_to_world.project_point3(ndc.extend(1.0));
+
+        MouseRay {
+            near,
+            far,
+            direction: far - near,
+        }
+    }
+
+    /// The entity of the first collider the ray hits between the near and far planes, if any
+    pub fn cast(
+        &self,
+        query_pipeline: &QueryPipeline,
+        collider_query: &QueryPipelineColliderComponentsQuery,
+    ) -> Option<Entity> {
+        let collider_set = QueryPipelineColliderComponentsSet(collider_query);
+        let ray = Ray::new(self.near.into(), self.direction.into());
+
+        // the ray direction spans the near to far planes, so a toi of 1 reaches the far plane
+        query_pipeline
+            .cast_ray(
+                &collider_set,
+                &ray,
+                1.0,
+                true,
+                InteractionGroups::all(),
+                None,
+            )
+            .map(|(handle, _)| handle.entity())
+    }
+}
+
 fn mouseray_system(
     windows: Res<Windows>,
+    inputs: Res<crate::input::MappedInput>,
     mut query: Query<(
         &Camera,
         &GlobalTransform,
@@ -265,52 +876,57 @@ fn mouseray_system(
 ) {
     for (camera, camera_transform, controller, mut mouseray, mut cursor) in query.iter_mut() {
         let window = windows.get(camera.window);
-        let cursor_position = window.and_then(|w| w.cursor_position());
+        // read through the input map so that recorded cursor movement is played back
+        let cursor_position = inputs.cursor_position();
 
         if let (Some(window), Some(cursor_position)) = (window, cursor_position) {
-            let camera_position = camera_transform.compute_matrix();
-
             let screen_size = Vec2::from([window.width() as f32, window.height() as f32]);
-            let projection_matrix = camera.projection_matrix;
-
-            // Normalized device coordinate cursor position from (-1, -1, -1) to (1, 1, 1)
-            let cursor_ndc = (cursor_position / screen_size) * 2.0 - Vec2::from([1.0, 1.0]);
-            let cursor_pos_ndc_near: Vec3 = cursor_ndc.extend(-1.0);
-            let cursor_pos_ndc_far: Vec3 = cursor_ndc.extend(1.0);
-
-            // Use near and far ndc points to generate a ray in world space
-            // This method is more robust than using the location of the camera as the start of
-            // the ray, because ortho cameras have a focal point at infinity!
-            let ndc_to_world: Mat4 = camera_position * projection_matrix.inverse();
-            let near: Vec3 = ndc_to_world.project_point3(cursor_pos_ndc_near);
-            let far: Vec3 = ndc_to_world.project_point3(cursor_pos_ndc_far);
-            let direction = far - near;
-
-            let ray = MouseRay {
-                near,
-                far,
-                direction,
-            };
+            let ray = MouseRay::from_screen(camera, camera_transform, screen_size, cursor_position);
 
             let _ = mouseray.insert(ray);
 
-            let d = (-ray.direction).dot(Vec3::Y);
-
-            if d == 0f32 {
-                continue;
-            }
-
-            let t = Vec3::Y.dot(ray.near - controller.focus) / d;
-            if t < 0f32 || t > 1f32 {
-                continue;
+            match (inputs.motion(Controls::Altitude), cursor.as_mut()) {
+                // the cursor stays over the same point of the plane while its height is changed
+                (Some(motion), Some(cursor)) => {
+                    cursor.height -= motion.y * controller.radius * ALTITUDE_RATE;
+                }
+                _ => {
+                    let d = (-ray.direction).dot(Vec3::Y);
+
+                    if d == 0f32 {
+                        continue;
+                    }
+
+                    let t = Vec3::Y.dot(ray.near - controller.focus) / d;
+                    if t < 0f32 || t > 1f32 {
+                        continue;
+                    }
+
+                    let plane = ray.near + ray.direction * t;
+                    let height = cursor.as_ref().map_or(0.0, |cursor| cursor.height);
+
+                    let _ = cursor.insert(ControlCursor {
+                        pos: plane,
+                        plane,
+                        height,
+                    });
+                }
             }
 
-            let pos = ray.near + ray.direction * t;
+            if let Some(cursor) = cursor.as_mut() {
+                cursor.pos = cursor.plane + Vec3::Y * cursor.height;
 
-            let _ = cursor.insert(ControlCursor { pos });
+                let (plane, pos) = (cursor.plane, cursor.pos);
+                lines.line(plane - Vec3::X - Vec3::Z, plane + Vec3::X + Vec3::Z, 1.0);
+                lines.line(plane - Vec3::Z + Vec3::X, plane + Vec3::Z - Vec3::X, 1.0);
 
-            lines.line(pos - Vec3::X - Vec3::Z, pos + Vec3::X + Vec3::Z, 1.0);
-            lines.line(pos - Vec3::Z + Vec3::X, pos + Vec3::Z - Vec3::X, 1.0);
+                if cursor.height != 0.0 {
+                    // height stalk from the plane up or down to the cursor
+                    lines.line(plane, pos, 0.0);
+                    lines.line(pos - Vec3::X, pos + Vec3::X, 0.0);
+                    lines.line(pos - Vec3::Z, pos + Vec3::Z, 0.0);
+                }
+            }
         }
     }
 }
@@ -1,23 +1,94 @@
 use crate::{
-    input::{MappedInput, Switch},
-    player::camera::ControlCursor,
-    units::MoveTarget,
+    input::{Gesture, MappedInput, Modifier, Switch},
+    player::camera::{ControlCursor, MouseRay},
+    units::{formation_slots, Faction, Formation, Order, OrderQueue, UnitClass},
     SystemLabels,
 };
 use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use std::collections::HashMap;
 
 pub struct CommandPlugin;
 
 impl Plugin for CommandPlugin {
     fn build(&self, app: &mut AppBuilder) {
-        app.add_startup_system(setup.system())
-            .add_system(commands.system().after(SystemLabels::Input));
+        // the commanding context goes on top of the selection context
+        app.add_startup_system(setup.system().after(SystemLabels::Selection))
+            .add_system(commands.system().after(SystemLabels::Input))
+            .add_system(cycle_formation.system().after(SystemLabels::Input))
+            .insert_resource(FormationSettings::default())
+            .add_system(draw_order_queues.system());
     }
 }
 
-#[derive(Debug, num_derive::ToPrimitive)]
+/// Input context for issuing orders to the selected units
+pub const COMMANDING_CONTEXT: &str = "Commanding";
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, num_derive::ToPrimitive)]
 pub enum Orders {
+    /// Move to the cursor
     Move,
+    /// Patrol between the unit's position and the cursor
+    Patrol,
+    /// Follow the unit under the cursor
+    Follow,
+    /// Guard the unit under the cursor
+    Guard,
+    Stop,
+    HoldPosition,
+    /// Follow or guard a friendly unit under the cursor, or move to the cursor otherwise
+    Context,
+    /// While held, orders are added to the end of the selected units' orders rather than
+    /// replacing them
+    Queue,
+    /// Switch to the next formation for group moves
+    CycleFormation,
+}
+
+/// The formation the selected units move in when ordered to move together
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FormationSettings {
+    pub formation: Formation,
+    /// Distance between neighbouring units in a formation
+    pub spacing: f32,
+}
+
+impl Default for FormationSettings {
+    fn default() -> Self {
+        FormationSettings {
+            formation: Formation::Preserve,
+            spacing: 3.0,
+        }
+    }
+}
+
+impl FormationSettings {
+    /// The destination of each unit of a group moving to `target`, given where each unit starts
+    /// its move from
+    pub fn destinations(
+        &self,
+        units: impl IntoIterator<Item = (Entity, Vec3)>,
+        target: Vec3,
+    ) -> HashMap<Entity, Vec3> {
+        let (entities, positions): (Vec<Entity>, Vec<Vec3>) = units.into_iter().unzip();
+        let slots = formation_slots(self.formation, self.spacing, &positions, target);
+
+        entities.into_iter().zip(slots).collect()
+    }
+}
+
+/// Where a unit starts its next order from: the end of its queued orders when queueing, or its
+/// current position otherwise
+pub fn order_origin(orders: &OrderQueue, position: Vec3, queue: bool) -> Vec3 {
+    if queue {
+        orders
+            .iter()
+            .filter_map(Order::waypoint)
+            .last()
+            .unwrap_or(position)
+    } else {
+        position
+    }
 }
 
 fn setup(mut inputs: ResMut<MappedInput>) {
@@ -25,19 +96,165 @@ fn setup(mut inputs: ResMut<MappedInput>) {
         [Switch::Key(KeyCode::M), MouseButton::Left.into()],
         Orders::Move,
     );
+    inputs.bind(
+        [Switch::Key(KeyCode::P), MouseButton::Left.into()],
+        Orders::Patrol,
+    );
+    inputs.bind(
+        [Switch::Key(KeyCode::T), MouseButton::Left.into()],
+        Orders::Follow,
+    );
+    inputs.bind(
+        [Switch::Key(KeyCode::G), MouseButton::Left.into()],
+        Orders::Guard,
+    );
+    inputs.bind([KeyCode::X], Orders::Stop);
+    inputs.bind([KeyCode::H], Orders::HoldPosition);
+    // a tap, so that orbiting with the right mouse button doesn't also issue orders
+    inputs.bind_gesture(
+        [MouseButton::Right],
+        Orders::Context,
+        Gesture::Tap { max: 0.2 },
+    );
+    inputs.bind([Modifier::Shift], Orders::Queue);
+    inputs.bind([KeyCode::V], Orders::CycleFormation);
+
+    inputs.assign_context::<Orders>(COMMANDING_CONTEXT);
+    // consumes, so that e.g. M + click orders a move without also selecting with the click
+    inputs.push_context(COMMANDING_CONTEXT, true);
+}
+
+fn cycle_formation(inputs: Res<MappedInput>, mut settings: ResMut<FormationSettings>) {
+    if inputs.just_activated(Orders::CycleFormation) {
+        settings.formation = settings.formation.next();
+        log::info!("Formation: {:?}", settings.formation);
+    }
 }
 
 fn commands(
-    mut commands: Commands,
     inputs: Res<MappedInput>,
-    cursor: Query<&Option<ControlCursor>>,
-    selected_units: Query<Entity, With<crate::units::Selected>>,
+    formation: Res<FormationSettings>,
+    query_pipeline: Res<QueryPipeline>,
+    collider_query: QueryPipelineColliderComponentsQuery,
+    mut cursor: Query<(&Option<MouseRay>, &mut Option<ControlCursor>)>,
+    units: Query<(&Faction, &UnitClass)>,
+    mut selected_units: Query<
+        (Entity, &GlobalTransform, &mut OrderQueue),
+        With<crate::units::Selected>,
+    >,
+) {
+    let action = if inputs.just_released(Orders::Move) {
+        Orders::Move
+    } else if inputs.just_released(Orders::Patrol) {
+        Orders::Patrol
+    } else if inputs.just_released(Orders::Follow) {
+        Orders::Follow
+    } else if inputs.just_released(Orders::Guard) {
+        Orders::Guard
+    } else if inputs.just_activated(Orders::Stop) {
+        Orders::Stop
+    } else if inputs.just_activated(Orders::HoldPosition) {
+        Orders::HoldPosition
+    } else if inputs.just_activated(Orders::Context) {
+        Orders::Context
+    } else {
+        return;
+    };
+
+    let (mouseray, mut cursor) = match cursor.single_mut() {
+        Ok(cursor) => cursor,
+        Err(_) => return,
+    };
+
+    let cursor = match cursor.as_mut() {
+        Some(cursor) => cursor,
+        None => return,
+    };
+
+    let pos = cursor.pos;
+    let target = mouseray
+        .as_ref()
+        .and_then(|ray| ray.cast(&query_pipeline, &collider_query));
+
+    let action = match action {
+        // friendly units are escorted, following ships and guarding structures. Anything else
+        // under the cursor is moved to like empty space.
+        Orders::Context => match target.and_then(|unit| units.get(unit).ok()) {
+            Some((Faction::Player, class)) if class.is_mobile() => Orders::Follow,
+            Some((Faction::Player, _)) => Orders::Guard,
+            _ => Orders::Move,
+        },
+        action => action,
+    };
+
+    let queue = inputs.active(Orders::Queue);
+
+    // group moves spread the units out in formation, rather than sending them all to one point
+    let destinations = if action == Orders::Move {
+        formation.destinations(
+            selected_units
+                .iter_mut()
+                .map(|(entity, transform, orders)| {
+                    (entity, order_origin(&orders, transform.translation, queue))
+                }),
+            pos,
+        )
+    } else {
+        HashMap::new()
+    };
+
+    for (entity, transform, mut orders) in selected_units.iter_mut() {
+        let issued = match (action, target) {
+            (Orders::Move, _) => vec![Order::Move(
+                destinations.get(&entity).copied().unwrap_or(pos),
+            )],
+            // a new patrol loops between the unit's position and the cursor, while queued patrol
+            // orders extend the loop
+            (Orders::Patrol, _) if queue => vec![Order::Patrol(pos)],
+            (Orders::Patrol, _) => vec![Order::Patrol(pos), Order::Patrol(transform.translation)],
+            // units can't follow or guard themselves
+            (Orders::Follow, Some(target)) if target != entity => vec![Order::Follow(target)],
+            (Orders::Guard, Some(target)) if target != entity => vec![Order::Guard(target)],
+            (Orders::Stop, _) => vec![Order::Stop],
+            (Orders::HoldPosition, _) => vec![Order::HoldPosition(transform.translation)],
+            _ => continue,
+        };
+
+        log::debug!("commanding unit {:?} to {:?}", entity, issued);
+
+        if !queue {
+            orders.clear();
+        }
+
+        for order in issued {
+            orders.push(order);
+        }
+    }
+
+    // the next order starts back on the control plane
+    cursor.height = 0.0;
+}
+
+/// Draws the path through the queued orders of each unit
+fn draw_order_queues(
+    mut lines: ResMut<bevy_prototype_debug_lines::DebugLines>,
+    units: Query<(&GlobalTransform, &OrderQueue)>,
+    transforms: Query<&GlobalTransform>,
 ) {
-    if inputs.just_deactivated(Orders::Move) {
-        if let Ok(Some(ControlCursor { pos })) = cursor.single() {
-            for entity in selected_units.iter() {
-                log::debug!("commanding unit {:?} to position {:?}", entity, pos);
-                commands.entity(entity).insert(MoveTarget(*pos));
+    for (transform, orders) in units.iter() {
+        let mut from = transform.translation;
+
+        for order in orders.iter() {
+            let to = order.waypoint().or_else(|| {
+                order
+                    .entity()
+                    .and_then(|entity| transforms.get(entity).ok())
+                    .map(|transform| transform.translation)
+            });
+
+            if let Some(to) = to {
+                lines.line(from, to, 0.0);
+                from = to;
             }
         }
     }
@@ -0,0 +1,195 @@
+//! # Control groups
+//! Numbered groups of units which the player can save and recall. Holding Ctrl and pressing a
+//! number key assigns the selected units to that group, holding Shift adds them to it, and
+//! pressing the number alone selects the group again. Double tapping the number also moves the
+//! camera to the group.
+//!
+//! Units which are despawned are pruned from their groups.
+use bevy::prelude::*;
+use num_traits::ToPrimitive;
+
+use crate::{
+    input::{Gesture, MappedInput, Modifier, Switch},
+    player::{
+        camera::{CameraController, CameraFollow},
+        selection::SELECTION_CONTEXT,
+    },
+    units::Selected,
+    SystemLabels,
+};
+
+pub struct ControlGroupPlugin;
+
+impl Plugin for ControlGroupPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_startup_system(setup.system())
+            .add_system(prune_groups.system().before(SystemLabels::Input))
+            .add_system(
+                control_groups
+                    .system()
+                    .after(SystemLabels::Input)
+                    .before(SystemLabels::Camera),
+            )
+            .insert_resource(ControlGroups::default());
+    }
+}
+
+/// Number of control groups, one per number key
+pub const GROUP_COUNT: usize = 10;
+
+/// Number keys of the groups, by group number
+const GROUP_KEYS: [KeyCode; GROUP_COUNT] = [
+    KeyCode::Key0,
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+    KeyCode::Key9,
+];
+
+/// Actions on a control group, by group number
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum Groups {
+    /// Replace the group with the selected units
+    Assign(u8),
+    /// Add the selected units to the group
+    Append(u8),
+    /// Select the units of the group
+    Recall(u8),
+    /// Move the camera to the group
+    Focus(u8),
+}
+
+// Deriving ToPrimitive only works for fieldless enums, so each kind of action gets its own range
+// of ids
+impl ToPrimitive for Groups {
+    fn to_i64(&self) -> Option<i64> {
+        self.to_u64().map(|id| id as i64)
+    }
+
+    fn to_u64(&self) -> Option<u64> {
+        let (kind, group) = match *self {
+            Groups::Assign(group) => (0, group),
+            Groups::Append(group) => (1, group),
+            Groups::Recall(group) => (2, group),
+            Groups::Focus(group) => (3, group),
+        };
+
+        Some(kind * GROUP_COUNT as u64 + group as u64)
+    }
+}
+
+/// The units of each control group, by group number
+#[derive(Debug, Clone, Default)]
+pub struct ControlGroups {
+    pub groups: [Vec<Entity>; GROUP_COUNT],
+}
+
+impl ControlGroups {
+    pub fn assign(&mut self, group: u8, units: impl IntoIterator<Item = Entity>) {
+        let members = &mut self.groups[group as usize];
+        members.clear();
+        members.extend(units);
+    }
+
+    pub fn append(&mut self, group: u8, units: impl IntoIterator<Item = Entity>) {
+        let members = &mut self.groups[group as usize];
+
+        for unit in units {
+            if !members.contains(&unit) {
+                members.push(unit);
+            }
+        }
+    }
+
+    pub fn get(&self, group: u8) -> &[Entity] {
+        &self.groups[group as usize]
+    }
+}
+
+fn setup(mut inputs: ResMut<MappedInput>) {
+    for (group, &key) in GROUP_KEYS.iter().enumerate() {
+        let group = group as u8;
+
+        inputs.bind(
+            [Switch::from(Modifier::Ctrl), key.into()],
+            Groups::Assign(group),
+        );
+        inputs.bind(
+            [Switch::from(Modifier::Shift), key.into()],
+            Groups::Append(group),
+        );
+        inputs.bind([key], Groups::Recall(group));
+        inputs.bind_gesture(
+            [key],
+            Groups::Focus(group),
+            Gesture::DoubleTap { window: 0.3 },
+        );
+    }
+
+    inputs.assign_context::<Groups>(SELECTION_CONTEXT);
+}
+
+/// Removes despawned units from their groups
+fn prune_groups(mut groups: ResMut<ControlGroups>, units: Query<Entity>) {
+    for members in groups.groups.iter_mut() {
+        members.retain(|&unit| units.get(unit).is_ok());
+    }
+}
+
+fn control_groups(
+    mut commands: Commands,
+    inputs: Res<MappedInput>,
+    mut groups: ResMut<ControlGroups>,
+    selected: Query<Entity, With<Selected>>,
+    transforms: Query<&GlobalTransform>,
+    mut cameras: Query<(Entity, &mut CameraController)>,
+) {
+    for group in 0..GROUP_COUNT as u8 {
+        if inputs.just_activated(Groups::Assign(group)) {
+            groups.assign(group, selected.iter());
+            log::info!("Assigned control group {}", group);
+        } else if inputs.just_activated(Groups::Append(group)) {
+            groups.append(group, selected.iter());
+            log::info!("Added to control group {}", group);
+        } else if inputs.just_activated(Groups::Recall(group)) {
+            let members = groups.get(group);
+
+            // recalling an empty group keeps the current selection
+            if !members.is_empty() {
+                for unit in selected.iter() {
+                    commands.entity(unit).remove::<Selected>();
+                }
+
+                for &unit in members {
+                    commands.entity(unit).insert(Selected);
+                }
+            }
+        }
+
+        if inputs.just_activated(Groups::Focus(group)) {
+            let positions: Vec<Vec3> = groups
+                .get(group)
+                .iter()
+                .filter_map(|&unit| transforms.get(unit).ok())
+                .map(|transform| transform.translation)
+                .collect();
+
+            if positions.is_empty() {
+                continue;
+            }
+
+            let centroid =
+                positions.iter().fold(Vec3::ZERO, |sum, &p| sum + p) / positions.len() as f32;
+
+            for (camera, mut controller) in cameras.iter_mut() {
+                commands.entity(camera).remove::<CameraFollow>();
+                controller.target_focus = centroid;
+            }
+        }
+    }
+}
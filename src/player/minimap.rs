@@ -0,0 +1,532 @@
+//! # Minimap
+//! A top-down overview of the playable volume in the corner of the screen, showing every unit in
+//! its faction's colour and the footprint of the camera view on the control plane.
+//!
+//! Left clicking (or dragging) on the minimap moves the camera focus, and right clicking orders
+//! the selected units to move there. While the cursor is over the minimap its input context is
+//! pushed with `consume` set, so these clicks don't also act on the world behind it.
+//!
+//! The projection between world and minimap coordinates is kept in [`MinimapProjection`], which
+//! doesn't depend on any window or ECS state.
+use std::collections::HashMap;
+
+use bevy::{prelude::*, render::camera::Camera};
+
+use crate::{
+    input::{MappedInput, Modifier, Switch},
+    player::{
+        camera::{CameraController, CameraFollow, CameraSettings, MouseRay},
+        commands::{order_origin, FormationSettings},
+    },
+    units::{Faction, Order, OrderQueue, Selected},
+    SystemLabels,
+};
+
+pub struct MinimapPlugin;
+
+impl Plugin for MinimapPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_startup_system(setup.system())
+            .add_system(minimap_hover.system().after(SystemLabels::Input))
+            .add_system(
+                minimap_input
+                    .system()
+                    .after(SystemLabels::Input)
+                    .before(SystemLabels::Camera),
+            )
+            .add_system(minimap_blips.system())
+            .add_system(minimap_footprint.system().after(SystemLabels::Camera))
+            .insert_resource(UnitBlips::default());
+    }
+}
+
+/// Input context enabled while the cursor is over the minimap
+pub const MINIMAP_CONTEXT: &str = "Minimap";
+
+/// Width and height of the minimap, in pixels
+const MINIMAP_SIZE: f32 = 200.0;
+/// Distance of the minimap from the bottom right corner of the window, in pixels
+const MINIMAP_MARGIN: f32 = 10.0;
+const BLIP_SIZE: f32 = 4.0;
+const FOOTPRINT_THICKNESS: f32 = 1.0;
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, num_derive::ToPrimitive)]
+pub enum Minimap {
+    /// Move the camera focus to the point under the cursor
+    Focus,
+    /// Order the selected units to the point under the cursor
+    Move,
+    /// Queue a move to the point under the cursor after the selected units' orders
+    QueueMove,
+}
+
+/// Maps between positions in the world and positions on the minimap. The minimap looks down on
+/// the world with -Z towards the top, and minimap coordinates are in pixels from its bottom left
+/// corner.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MinimapProjection {
+    /// Corners of the area shown, as world x and z
+    pub world_min: Vec2,
+    pub world_max: Vec2,
+    /// Size of the minimap in pixels
+    pub size: Vec2,
+}
+
+impl MinimapProjection {
+    pub fn world_to_map(&self, world: Vec3) -> Vec2 {
+        let extent = self.world_max - self.world_min;
+        let x = (world.x - self.world_min.x) / extent.x;
+        let y = (self.world_max.y - world.z) / extent.y;
+
+        Vec2::new(x, y) * self.size
+    }
+
+    /// The world position of a minimap position, at the given height
+    pub fn map_to_world(&self, map: Vec2, height: f32) -> Vec3 {
+        let extent = self.world_max - self.world_min;
+        let fraction = map / self.size;
+
+        Vec3::new(
+            self.world_min.x + fraction.x * extent.x,
+            height,
+            self.world_max.y - fraction.y * extent.y,
+        )
+    }
+
+    pub fn contains(&self, map: Vec2) -> bool {
+        map.x >= 0.0 && map.y >= 0.0 && map.x <= self.size.x && map.y <= self.size.y
+    }
+
+    /// Clamp a minimap position to the edges of the minimap
+    pub fn clamp(&self, map: Vec2) -> Vec2 {
+        map.max(Vec2::ZERO).min(self.size)
+    }
+}
+
+/// Where a ray meets the horizontal plane at `height`. Rays which don't reach the plane before
+/// their far point, such as those looking above the horizon, end at the far point instead.
+pub fn ground_point(ray: &MouseRay, height: f32) -> Vec3 {
+    let d = ray.direction.y;
+
+    if d != 0.0 {
+        let t = (height - ray.near.y) / d;
+        if (0.0..=1.0).contains(&t) {
+            return ray.near + ray.direction * t;
+        }
+    }
+
+    ray.far
+}
+
+impl From<&CameraSettings> for MinimapProjection {
+    fn from(settings: &CameraSettings) -> Self {
+        MinimapProjection {
+            world_min: Vec2::new(settings.bounds_min.x, settings.bounds_min.z),
+            world_max: Vec2::new(settings.bounds_max.x, settings.bounds_max.z),
+            size: Vec2::new(MINIMAP_SIZE, MINIMAP_SIZE),
+        }
+    }
+}
+
+/// The bottom left corner of the minimap in window coordinates
+fn minimap_origin(window: &Window) -> Vec2 {
+    Vec2::new(
+        window.width() - MINIMAP_MARGIN - MINIMAP_SIZE,
+        MINIMAP_MARGIN,
+    )
+}
+
+/// The minimap panel, which blips and the footprint are children of
+pub struct MinimapPanel;
+
+/// One edge of the camera footprint
+pub struct FootprintEdge(usize);
+
+/// Blip entity of each unit shown on the minimap
+#[derive(Default)]
+pub struct UnitBlips {
+    blips: HashMap<Entity, Entity>,
+    materials: HashMap<Faction, Handle<ColorMaterial>>,
+}
+
+fn setup(
+    mut commands: Commands,
+    mut inputs: ResMut<MappedInput>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    inputs.bind([MouseButton::Left], Minimap::Focus);
+    inputs.bind([MouseButton::Right], Minimap::Move);
+    inputs.bind(
+        [Switch::from(Modifier::Shift), MouseButton::Right.into()],
+        Minimap::QueueMove,
+    );
+    inputs.assign_context::<Minimap>(MINIMAP_CONTEXT);
+
+    commands.spawn_bundle(UiCameraBundle::default());
+
+    let footprint = materials.add(Color::rgba(1.0, 1.0, 1.0, 0.8).into());
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Px(MINIMAP_SIZE), Val::Px(MINIMAP_SIZE)),
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    right: Val::Px(MINIMAP_MARGIN),
+                    bottom: Val::Px(MINIMAP_MARGIN),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            material: materials.add(Color::rgba(0.0, 0.05, 0.1, 0.8).into()),
+            ..Default::default()
+        })
+        .insert(MinimapPanel)
+        .with_children(|panel| {
+            for edge in 0..4 {
+                panel
+                    .spawn_bundle(NodeBundle {
+                        style: Style {
+                            position_type: PositionType::Absolute,
+                            ..Default::default()
+                        },
+                        material: footprint.clone(),
+                        ..Default::default()
+                    })
+                    .insert(FootprintEdge(edge));
+            }
+        });
+}
+
+/// Enables the minimap context while the cursor is over the minimap
+fn minimap_hover(
+    windows: Res<Windows>,
+    settings: Res<CameraSettings>,
+    mut inputs: ResMut<MappedInput>,
+) {
+    let projection = MinimapProjection::from(&*settings);
+
+    let hovered = match (windows.get_primary(), inputs.cursor_position()) {
+        (Some(window), Some(cursor)) => projection.contains(cursor - minimap_origin(window)),
+        _ => false,
+    };
+
+    // keep the context while a click which started on the minimap is held, so dragging the
+    // focus past the edge doesn't start acting on the world
+    let held = inputs.active(Minimap::Focus)
+        || inputs.active(Minimap::Move)
+        || inputs.active(Minimap::QueueMove);
+
+    if hovered && !inputs.context_enabled(MINIMAP_CONTEXT) {
+        inputs.push_context(MINIMAP_CONTEXT, true);
+    } else if !hovered && !held && inputs.context_enabled(MINIMAP_CONTEXT) {
+        inputs.remove_context(MINIMAP_CONTEXT);
+    }
+}
+
+fn minimap_input(
+    mut commands: Commands,
+    windows: Res<Windows>,
+    settings: Res<CameraSettings>,
+    inputs: Res<MappedInput>,
+    formation: Res<FormationSettings>,
+    mut cameras: Query<(Entity, &mut CameraController)>,
+    mut selected: Query<(Entity, &GlobalTransform, &mut OrderQueue), With<Selected>>,
+) {
+    let focus = inputs.active(Minimap::Focus);
+    let queue = inputs.just_activated(Minimap::QueueMove);
+    let order = inputs.just_activated(Minimap::Move) || queue;
+
+    if !focus && !order {
+        return;
+    }
+
+    let (window, cursor) = match (windows.get_primary(), inputs.cursor_position()) {
+        (Some(window), Some(cursor)) => (window, cursor),
+        _ => return,
+    };
+
+    let projection = MinimapProjection::from(&*settings);
+    let map = projection.clamp(cursor - minimap_origin(window));
+
+    for (entity, mut controller) in cameras.iter_mut() {
+        // points picked on the minimap lie on the control plane
+        let target = projection.map_to_world(map, controller.focus.y);
+
+        if focus {
+            commands.entity(entity).remove::<CameraFollow>();
+            controller.target_focus = target;
+        }
+
+        if order {
+            let destinations = formation.destinations(
+                selected.iter_mut().map(|(unit, transform, orders)| {
+                    (unit, order_origin(&orders, transform.translation, queue))
+                }),
+                target,
+            );
+
+            for (unit, _, mut orders) in selected.iter_mut() {
+                let order = Order::Move(destinations.get(&unit).copied().unwrap_or(target));
+
+                if queue {
+                    orders.push(order);
+                } else {
+                    orders.replace(order);
+                }
+            }
+        }
+    }
+}
+
+fn minimap_blips(
+    mut commands: Commands,
+    settings: Res<CameraSettings>,
+    mut blips: ResMut<UnitBlips>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    panel: Query<Entity, With<MinimapPanel>>,
+    units: Query<(Entity, &GlobalTransform, &Faction)>,
+    mut styles: Query<&mut Style>,
+) {
+    let panel = match panel.single() {
+        Ok(panel) => panel,
+        Err(_) => return,
+    };
+
+    let projection = MinimapProjection::from(&*settings);
+    let UnitBlips {
+        blips,
+        materials: faction_materials,
+    } = &mut *blips;
+
+    // remove the blips of units which no longer exist
+    blips.retain(|&unit, &mut blip| {
+        let exists = units.get(unit).is_ok();
+        if !exists {
+            commands.entity(blip).despawn();
+        }
+        exists
+    });
+
+    for (unit, transform, &faction) in units.iter() {
+        let map = projection.world_to_map(transform.translation);
+        let visible = projection.contains(map);
+        let position = Rect {
+            left: Val::Px(map.x - BLIP_SIZE / 2.0),
+            bottom: Val::Px(map.y - BLIP_SIZE / 2.0),
+            ..Default::default()
+        };
+
+        match blips.get(&unit) {
+            Some(&blip) => {
+                if let Ok(mut style) = styles.get_mut(blip) {
+                    style.position = position;
+                    style.display = if visible {
+                        Display::Flex
+                    } else {
+                        Display::None
+                    };
+                }
+            }
+            None => {
+                let material = faction_materials
+                    .entry(faction)
+                    .or_insert_with(|| materials.add(faction.color().into()))
+                    .clone();
+
+                let blip = commands
+                    .spawn_bundle(NodeBundle {
+                        style: Style {
+                            size: Size::new(Val::Px(BLIP_SIZE), Val::Px(BLIP_SIZE)),
+                            position_type: PositionType::Absolute,
+                            position,
+                            display: if visible {
+                                Display::Flex
+                            } else {
+                                Display::None
+                            },
+                            ..Default::default()
+                        },
+                        material,
+                        ..Default::default()
+                    })
+                    .id();
+
+                commands.entity(panel).push_children(&[blip]);
+                blips.insert(unit, blip);
+            }
+        }
+    }
+}
+
+/// Outlines the area of the control plane visible to the camera
+fn minimap_footprint(
+    windows: Res<Windows>,
+    settings: Res<CameraSettings>,
+    cameras: Query<(&Camera, &GlobalTransform, &CameraController)>,
+    mut edges: Query<(&FootprintEdge, &mut Style)>,
+) {
+    let (camera, camera_transform, controller) = match cameras.single() {
+        Ok(camera) => camera,
+        Err(_) => return,
+    };
+
+    let window = match windows.get(camera.window) {
+        Some(window) => window,
+        None => return,
+    };
+
+    let projection = MinimapProjection::from(&*settings);
+    let window_size = Vec2::new(window.width(), window.height());
+
+    let mut min = Vec2::splat(f32::INFINITY);
+    let mut max = Vec2::splat(f32::NEG_INFINITY);
+
+    for corner in [Vec2::ZERO, Vec2::X, Vec2::ONE, Vec2::Y].iter() {
+        let ray =
+            MouseRay::from_screen(camera, camera_transform, window_size, *corner * window_size);
+        let map = projection.world_to_map(ground_point(&ray, controller.focus.y));
+
+        min = min.min(map);
+        max = max.max(map);
+    }
+
+    let min = projection.clamp(min);
+    let max = projection.clamp(max);
+    let size = max - min;
+
+    for (&FootprintEdge(edge), mut style) in edges.iter_mut() {
+        // bottom, top, left, right
+        let (position, extent) = match edge {
+            0 => (min, Vec2::new(size.x, FOOTPRINT_THICKNESS)),
+            1 => (
+                Vec2::new(min.x, max.y),
+                Vec2::new(size.x, FOOTPRINT_THICKNESS),
+            ),
+            2 => (min, Vec2::new(FOOTPRINT_THICKNESS, size.y)),
+            _ => (
+                Vec2::new(max.x, min.y),
+                Vec2::new(FOOTPRINT_THICKNESS, size.y),
+            ),
+        };
+
+        style.position = Rect {
+            left: Val::Px(position.x),
+            bottom: Val::Px(position.y),
+            ..Default::default()
+        };
+        style.size = Size::new(Val::Px(extent.x), Val::Px(extent.y));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn projection() -> MinimapProjection {
+        MinimapProjection {
+            world_min: Vec2::new(-500.0, -250.0),
+            world_max: Vec2::new(500.0, 250.0),
+            size: Vec2::new(200.0, 100.0),
+        }
+    }
+
+    fn assert_near(a: Vec3, b: Vec3) {
+        assert!(a.distance(b) < 1e-3, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn corners() {
+        let projection = projection();
+
+        // -Z is towards the top of the minimap
+        let corners = [
+            (Vec3::new(-500.0, 0.0, 250.0), Vec2::new(0.0, 0.0)),
+            (Vec3::new(500.0, 0.0, 250.0), Vec2::new(200.0, 0.0)),
+            (Vec3::new(-500.0, 0.0, -250.0), Vec2::new(0.0, 100.0)),
+            (Vec3::new(500.0, 0.0, -250.0), Vec2::new(200.0, 100.0)),
+        ];
+
+        for &(world, map) in corners.iter() {
+            assert_eq!(projection.world_to_map(world), map);
+            assert_near(projection.map_to_world(map, 0.0), world);
+        }
+
+        assert_eq!(projection.world_to_map(Vec3::ZERO), Vec2::new(100.0, 50.0));
+    }
+
+    #[test]
+    fn round_trip() {
+        let projection = projection();
+
+        for &world in [
+            Vec3::new(12.5, 30.0, -80.0),
+            Vec3::new(-499.0, -10.0, 249.0),
+            Vec3::new(320.0, 0.0, 10.0),
+        ]
+        .iter()
+        {
+            let map = projection.world_to_map(world);
+            assert!(projection.contains(map));
+            // the minimap is flat, so the height comes from the caller
+            assert_near(projection.map_to_world(map, world.y), world);
+        }
+    }
+
+    #[test]
+    fn clamp() {
+        let projection = projection();
+
+        assert!(!projection.contains(Vec2::new(-1.0, 50.0)));
+        assert_eq!(
+            projection.clamp(Vec2::new(-20.0, 50.0)),
+            Vec2::new(0.0, 50.0)
+        );
+        assert_eq!(
+            projection.clamp(Vec2::new(250.0, 120.0)),
+            Vec2::new(200.0, 100.0)
+        );
+        assert_eq!(
+            projection.clamp(Vec2::new(30.0, 40.0)),
+            Vec2::new(30.0, 40.0)
+        );
+
+        // points outside the world clamp to the edge of the minimap
+        let map = projection.clamp(projection.world_to_map(Vec3::new(900.0, 0.0, -900.0)));
+        assert_near(
+            projection.map_to_world(map, 0.0),
+            Vec3::new(500.0, 0.0, -250.0),
+        );
+    }
+
+    fn ray(near: Vec3, far: Vec3) -> MouseRay {
+        MouseRay {
+            near,
+            far,
+            direction: far - near,
+        }
+    }
+
+    #[test]
+    fn ground_point_hits_plane() {
+        let ray = ray(Vec3::new(0.0, 10.0, 0.0), Vec3::new(20.0, -10.0, 0.0));
+
+        assert_near(ground_point(&ray, 0.0), Vec3::new(10.0, 0.0, 0.0));
+        assert_near(ground_point(&ray, 5.0), Vec3::new(5.0, 5.0, 0.0));
+    }
+
+    #[test]
+    fn ground_point_misses_plane() {
+        // looking above the horizon
+        let up = ray(Vec3::new(0.0, 10.0, 0.0), Vec3::new(0.0, 20.0, -100.0));
+        assert_eq!(ground_point(&up, 0.0), up.far);
+
+        // parallel to the plane
+        let level = ray(Vec3::new(0.0, 10.0, 0.0), Vec3::new(0.0, 10.0, -100.0));
+        assert_eq!(ground_point(&level, 0.0), level.far);
+
+        // the plane is beyond the far point
+        let short = ray(Vec3::new(0.0, 10.0, 0.0), Vec3::new(0.0, 5.0, -100.0));
+        assert_eq!(ground_point(&short, 0.0), short.far);
+    }
+}
@@ -1,8 +1,11 @@
 use bevy::app::PluginGroupBuilder;
 use bevy::prelude::*;
 
+pub mod bookmarks;
 pub mod camera;
 pub mod commands;
+pub mod groups;
+pub mod minimap;
 pub mod selection;
 
 pub struct PlayerPluginGroup;
@@ -11,8 +14,11 @@ impl PluginGroup for PlayerPluginGroup {
     fn build(&mut self, group: &mut PluginGroupBuilder) {
         group
             .add(camera::CameraControlPlugin)
+            .add(bookmarks::BookmarkPlugin)
             .add(selection::SelectionPlugin)
-            .add(commands::CommandPlugin);
+            .add(groups::ControlGroupPlugin)
+            .add(commands::CommandPlugin)
+            .add(minimap::MinimapPlugin);
     }
 }
 //struct PlayerCamera {
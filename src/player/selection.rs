@@ -1,156 +1,526 @@
 //! # Selection
-//! The selection module handles both click and drag selection events
-use bevy::{
-    input::mouse::MouseButton,
-    math::*,
-    prelude::*,
-    render::camera::{Camera, PerspectiveProjection},
-};
+//! The selection module handles both click and drag selection events.
+//!
+//! Clicking or dragging replaces the selection. Holding Shift adds to the selection instead,
+//! and holding Ctrl removes dragged units from it, or toggles a single clicked unit. Double
+//! clicking a unit selects every unit of the same class and faction on screen.
+//!
+//! Drag selection tests the screen space bounds of [`units::Selectable`] units against the drag
+//! box, or the physics colliders under the box, depending on the [`SelectionBackend`].
+//! Drag selections are narrowed down by the [`SelectionFilter`] rules, so that e.g. dragging
+//! over a fleet parked next to a station only selects the fleet.
+use bevy::{math::*, prelude::*, render::camera::Camera};
 
 use bevy_rapier3d::prelude::*;
 
-use bevy_mod_picking::{
-    PickingEvent,
-    PickingEvent::Selection,
-    SelectionEvent::{JustDeselected, JustSelected},
+use crate::{
+    input::{Gesture, MappedInput, Modifier, Switch},
+    player::camera::{CameraController, MouseRay},
+    units,
 };
 
-use crate::units;
-
 pub struct SelectionPlugin;
 
+/// Input context of the selection controls
+pub const SELECTION_CONTEXT: &str = "Selection";
+
+/// Each action selects with the left mouse button, and decides how the clicked or dragged units
+/// are combined with the current selection
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, num_derive::ToPrimitive)]
+pub enum Select {
+    /// Select only the clicked or dragged units
+    Replace,
+    /// Add the clicked or dragged units to the selection
+    Add,
+    /// Remove the dragged units from the selection, or toggle a clicked unit
+    Remove,
+    /// Select every unit on screen of the same class and faction as the clicked unit
+    AllOfType,
+}
+
+const SELECT_MODES: [Select; 3] = [Select::Replace, Select::Add, Select::Remove];
+
 #[derive(Default, Debug, Copy, Clone)]
 pub struct DragCoords {
     start: Option<Vec2>,
     end: Option<Vec2>,
+    /// The selection action which started the drag
+    mode: Option<Select>,
+    /// Whether a unit was clicked since the drag started
+    clicked: bool,
 }
-#[derive(Default, Debug, Copy, Clone)]
-pub struct DragRays {
-    start: Option<crate::player::camera::MouseRay>,
-    end: Option<crate::player::camera::MouseRay>,
+
+/// Rules narrowing down which of the units inside a drag box are selected. Each rule only applies
+/// when the box contains units it prefers, otherwise everything in the box is selected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SelectionFilter {
+    /// Select only mobile units when the box contains both mobile units and structures
+    pub prefer_mobile: bool,
+    /// Select only the player's units when the box contains both own and other units
+    pub prefer_own: bool,
 }
+
+impl Default for SelectionFilter {
+    fn default() -> Self {
+        SelectionFilter {
+            prefer_mobile: true,
+            prefer_own: true,
+        }
+    }
+}
+
+impl SelectionFilter {
+    /// Apply the rules to the units inside a drag box
+    pub fn apply(&self, units: &[(Entity, units::UnitClass, units::Faction)]) -> Vec<Entity> {
+        let mut units = units.to_vec();
+
+        if self.prefer_own && units.iter().any(|&(_, _, f)| f == units::Faction::Player) {
+            units.retain(|&(_, _, f)| f == units::Faction::Player);
+        }
+
+        if self.prefer_mobile && units.iter().any(|&(_, c, _)| c.is_mobile()) {
+            units.retain(|&(_, c, _)| c.is_mobile());
+        }
+
+        units.into_iter().map(|(entity, _, _)| entity).collect()
+    }
+}
+
+/// How drag selection finds the units inside the drag box. Clicks always cast the mouse ray
+/// against the physics colliders, whichever backend is used, so a [`units::Selectable`] unit
+/// without a collider can be drag selected with [`SelectionBackend::ScreenSpace`] but not
+/// clicked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SelectionBackend {
+    /// Project the bounding sphere of every [`units::Selectable`] to the screen and test it
+    /// against the box
+    ScreenSpace,
+    /// Intersect the colliders in the physics world with the volume under the box
+    Colliders,
+}
+
+impl Default for SelectionBackend {
+    fn default() -> Self {
+        SelectionBackend::ScreenSpace
+    }
+}
+
+/// Drag boxes narrower or shorter than this many pixels are treated as clicks
+const MIN_DRAG_SIZE: f32 = 4.0;
+
 impl Plugin for SelectionPlugin {
     fn build(&self, app: &mut AppBuilder) {
-        app.add_system(selection.system())
-            .add_system(drag_selection.system())
+        // the selection context goes on top of the camera context
+        let setup = setup
+            .system()
+            .label(crate::SystemLabels::Selection)
+            .after(crate::SystemLabels::Camera);
+
+        app.add_startup_system(setup)
+            .add_system(
+                selection
+                    .system()
+                    .label(crate::SystemLabels::Selection)
+                    .after(crate::SystemLabels::Input)
+                    .after(crate::SystemLabels::MouseRay),
+            )
+            // a click starts a new drag, which has to happen before the click is recorded in it
+            .add_system(
+                drag_selection
+                    .system()
+                    .after(crate::SystemLabels::Input)
+                    .before(crate::SystemLabels::Selection),
+            )
             .add_system(debug_draw_frustum.system())
             .insert_resource(DragCoords::default())
-            .insert_resource(DragRays::default())
+            .insert_resource(SelectionFilter::default())
+            .insert_resource(SelectionBackend::default())
             .insert_resource(Option::<ConvexPolyhedron>::default());
     }
 }
 
-fn selection(mut commands: Commands, mut events: EventReader<PickingEvent>) {
-    for event in events.iter() {
-        match event {
-            Selection(JustSelected(entity)) => {
-                commands.entity(*entity).insert(units::Selected);
+fn setup(mut inputs: ResMut<MappedInput>) {
+    inputs.bind([MouseButton::Left], Select::Replace);
+    inputs.bind(
+        [Switch::from(Modifier::Shift), MouseButton::Left.into()],
+        Select::Add,
+    );
+    inputs.bind(
+        [Switch::from(Modifier::Ctrl), MouseButton::Left.into()],
+        Select::Remove,
+    );
+    inputs.bind_gesture(
+        [MouseButton::Left],
+        Select::AllOfType,
+        Gesture::DoubleTap { window: 0.3 },
+    );
+
+    inputs.assign_context::<Select>(SELECTION_CONTEXT);
+    inputs.push_context(SELECTION_CONTEXT, false);
+}
+
+/// The selection action pressed this frame, if any
+fn select_mode(inputs: &MappedInput) -> Option<Select> {
+    SELECT_MODES
+        .iter()
+        .copied()
+        .find(|&mode| inputs.just_activated(mode))
+}
+
+/// Whether a world position is inside the camera's view
+fn on_screen(camera: &Camera, camera_transform: &GlobalTransform, position: Vec3) -> bool {
+    let world_to_ndc = camera.projection_matrix * camera_transform.compute_matrix().inverse();
+    let ndc = world_to_ndc.project_point3(position);
+
+    ndc.x.abs() <= 1.0 && ndc.y.abs() <= 1.0 && (0.0..=1.0).contains(&ndc.z)
+}
+
+/// Applies clicks on selectable units according to the selection action pressed. The unit is
+/// found by casting the mouse ray, which is built from the cursor position in the input map, so
+/// that played back recordings select the same units. Units without a collider can't be clicked.
+fn selection(
+    mut commands: Commands,
+    inputs: Res<MappedInput>,
+    query_pipeline: Res<QueryPipeline>,
+    collider_query: QueryPipelineColliderComponentsQuery,
+    mut drag: ResMut<DragCoords>,
+    mouseray: Query<&Option<MouseRay>>,
+    selectables: Query<Entity, With<units::Selectable>>,
+    selected: Query<Entity, With<units::Selected>>,
+    cameras: Query<(&Camera, &GlobalTransform), With<CameraController>>,
+    units: Query<(Entity, &GlobalTransform, &units::UnitClass, &units::Faction)>,
+) {
+    // clicks outside of the selection context, e.g. on the minimap, don't select
+    let mode = match select_mode(&inputs) {
+        Some(mode) => mode,
+        None => return,
+    };
+
+    let entity = match mouseray
+        .single()
+        .ok()
+        .and_then(|ray| ray.as_ref())
+        .and_then(|ray| ray.cast(&query_pipeline, &collider_query))
+        .filter(|&entity| selectables.get(entity).is_ok())
+    {
+        Some(entity) => entity,
+        None => return,
+    };
+
+    drag.clicked = true;
+
+    if inputs.just_activated(Select::AllOfType) {
+        select_all_of_type(&mut commands, entity, &selected, &cameras, &units);
+        return;
+    }
+
+    match mode {
+        Select::Replace => {
+            for other in selected.iter().filter(|&other| other != entity) {
+                commands.entity(other).remove::<units::Selected>();
             }
-            Selection(JustDeselected(entity)) => {
-                commands.entity(*entity).remove::<units::Selected>();
+            commands.entity(entity).insert(units::Selected);
+        }
+        Select::Add => {
+            commands.entity(entity).insert(units::Selected);
+        }
+        Select::Remove => {
+            if selected.get(entity).is_ok() {
+                commands.entity(entity).remove::<units::Selected>();
+            } else {
+                commands.entity(entity).insert(units::Selected);
             }
-            _ => (),
+        }
+        Select::AllOfType => {}
+    }
+}
+
+/// Replaces the selection with every unit on screen of the same class and faction as `clicked`
+fn select_all_of_type(
+    commands: &mut Commands,
+    clicked: Entity,
+    selected: &Query<Entity, With<units::Selected>>,
+    cameras: &Query<(&Camera, &GlobalTransform), With<CameraController>>,
+    units: &Query<(Entity, &GlobalTransform, &units::UnitClass, &units::Faction)>,
+) {
+    let (camera, camera_transform) = match cameras.single() {
+        Ok(camera) => camera,
+        Err(_) => return,
+    };
+
+    let (class, faction) = match units.get(clicked) {
+        Ok((_, _, &class, &faction)) => (class, faction),
+        Err(_) => return,
+    };
+
+    for entity in selected.iter() {
+        commands.entity(entity).remove::<units::Selected>();
+    }
+
+    for (entity, transform, &other_class, &other_faction) in units.iter() {
+        if other_class == class
+            && other_faction == faction
+            && on_screen(camera, camera_transform, transform.translation)
+        {
+            commands.entity(entity).insert(units::Selected);
         }
     }
 }
 
-/// System handles dra
+/// Selects the units inside the frustum dragged out with one of the selection actions
 fn drag_selection(
     mut commands: Commands,
     windows: Res<Windows>,
-    input_mouse: Res<Input<MouseButton>>,
+    inputs: Res<MappedInput>,
     query_pipeline: Res<QueryPipeline>,
     collider_query: QueryPipelineColliderComponentsQuery,
-    mut drag: Local<DragCoords>,
-    mut dragrays: Local<DragRays>,
-    q: Query<(
-        &Camera,
-        &GlobalTransform,
-        &PerspectiveProjection,
-        &Option<crate::player::camera::MouseRay>,
-    )>,
-    mut deselect: Query<(Entity, With<crate::units::Selected>)>,
+    mut drag: ResMut<DragCoords>,
+    filter: Res<SelectionFilter>,
+    backend: Res<SelectionBackend>,
+    // the minimap's UI camera is a camera too
+    q: Query<(&Camera, &GlobalTransform), With<CameraController>>,
+    selected: Query<Entity, With<units::Selected>>,
+    units: Query<(&units::UnitClass, &units::Faction)>,
+    selectables: Query<(Entity, &GlobalTransform, &units::Selectable)>,
 ) {
-    let cursor_position = windows.get_primary().and_then(|w| w.cursor_position());
+    let cursor_position = inputs.cursor_position();
+
+    if let Some(mode) = select_mode(&inputs) {
+        *drag = DragCoords {
+            start: cursor_position,
+            end: cursor_position,
+            mode: Some(mode),
+            clicked: false,
+        };
+    }
 
-    let (camera, camera_transform, projection, &mouseray) = q.single().unwrap();
+    let mode = match drag.mode {
+        Some(mode) => mode,
+        None => return,
+    };
 
-    if input_mouse.just_pressed(MouseButton::Left) {
-        drag.start = cursor_position;
-        dragrays.start = mouseray;
+    if inputs.active(mode) {
+        drag.end = cursor_position.or(drag.end);
     }
 
-    if input_mouse.pressed(MouseButton::Left) {
-        drag.end = cursor_position;
-        dragrays.end = mouseray;
+    if !inputs.just_deactivated(mode) {
+        return;
     }
 
-    if input_mouse.just_released(MouseButton::Left) {
-        if let DragCoords {
-            start: Some(start),
-            end: Some(end),
-        } = *drag
-        {
-            if start.cmpeq(end).any() {
-                // start and end coords should not share x or y coords
-                return;
-            }
+    // a drag cancelled by the input map, e.g. when the window loses focus, selects nothing
+    if !inputs.just_released(mode) {
+        *drag = DragCoords::default();
+        return;
+    }
+
+    let DragCoords {
+        start,
+        end,
+        clicked,
+        ..
+    } = std::mem::take(&mut *drag);
 
-            for (entity, _) in deselect.iter_mut() {
+    let (start, end) = match (start, end) {
+        (Some(start), Some(end)) => (start, end),
+        _ => return,
+    };
+
+    let size = (end - start).abs();
+    if size.x < MIN_DRAG_SIZE || size.y < MIN_DRAG_SIZE {
+        // too thin to be a drag box, so this was a click. Clicking on empty space clears the
+        // selection.
+        if mode == Select::Replace && !clicked {
+            for entity in selected.iter() {
                 commands.entity(entity).remove::<units::Selected>();
             }
+        }
+        return;
+    }
 
-            //let (camera, camera_transform, projection, mouseray) = q.single().unwrap();
+    let (camera, camera_transform) = match q.single() {
+        Ok(camera) => camera,
+        Err(_) => return,
+    };
 
-            let max = Vec2::max(start, end);
-            let min = Vec2::min(start, end);
+    let window_size = match windows.get(camera.window) {
+        Some(window) => Vec2::new(window.width(), window.height()),
+        None => return,
+    };
 
-            let corners = [
-                vec2(min.x, min.y),
-                vec2(max.x, min.y),
-                vec2(max.x, max.y),
-                vec2(min.x, max.y),
-            ];
+    let min = Vec2::min(start, end);
+    let max = Vec2::max(start, end);
 
-            let mut points: Vec<Point<Real>> = Vec::with_capacity(8);
+    let entities = match *backend {
+        SelectionBackend::ScreenSpace => screen_space_entities(
+            camera,
+            camera_transform,
+            window_size,
+            min,
+            max,
+            &selectables,
+        ),
+        SelectionBackend::Colliders => {
+            let frustum = match selection_frustum(camera, camera_transform, window_size, min, max) {
+                Some(frustum) => frustum,
+                None => {
+                    log::warn!("Ignoring degenerate selection box {:?} to {:?}", min, max);
+                    return;
+                }
+            };
 
-            for corner in corners.iter() {
-                let ray = bevy_mod_raycast::Ray3d::from_screenspace(
-                    *corner,
-                    &windows,
-                    camera,
-                    camera_transform,
-                )
-                .expect("Failed to cast screen ray");
+            let entities = collider_entities(&query_pipeline, &collider_query, &frustum);
+            commands.insert_resource(Some(frustum));
+            entities
+        }
+    };
 
-                points.push((ray.origin() + ray.direction() * projection.near).into());
-                points.push((ray.origin() + ray.direction() * projection.far).into());
-            }
+    let inside: Vec<_> = entities
+        .into_iter()
+        .filter_map(|entity| {
+            units
+                .get(entity)
+                .ok()
+                .map(|(&class, &faction)| (entity, class, faction))
+        })
+        .collect();
 
-            let frustum =
-                bevy_rapier3d::prelude::ConvexPolyhedron::from_convex_mesh(points, FRUSTUM_INDICES)
-                    .unwrap();
-
-            commands.insert_resource(Some(frustum.clone()));
-
-            let collider_set = QueryPipelineColliderComponentsSet(&collider_query);
-            let groups = InteractionGroups::all();
-            let filter = None;
-
-            query_pipeline.intersections_with_shape(
-                &collider_set,
-                &[0.0, 0.0, 0.0].into(), // we constructed the frustum with worldspace coordinates
-                &frustum,
-                groups,
-                filter,
-                |handle| {
-                    println!("The entity {:?} intersects our shape.", handle.entity());
-                    commands.entity(handle.entity()).insert(units::Selected);
-                    true
-                },
-            );
+    if mode == Select::Replace {
+        for entity in selected.iter() {
+            commands.entity(entity).remove::<units::Selected>();
         }
     }
+
+    if mode == Select::Remove {
+        // removing deselects everything in the box, regardless of the filter rules
+        for (entity, _, _) in inside {
+            commands.entity(entity).remove::<units::Selected>();
+        }
+    } else {
+        for entity in filter.apply(&inside) {
+            commands.entity(entity).insert(units::Selected);
+        }
+    }
+}
+
+/// The window position of a world position, or `None` if it is outside of the camera's depth
+/// range, e.g. behind the camera
+fn world_to_screen(
+    camera: &Camera,
+    camera_transform: &GlobalTransform,
+    window_size: Vec2,
+    position: Vec3,
+) -> Option<Vec2> {
+    let world_to_ndc = camera.projection_matrix * camera_transform.compute_matrix().inverse();
+    let ndc = world_to_ndc.project_point3(position);
+
+    if !ndc.is_finite() || !(0.0..=1.0).contains(&ndc.z) {
+        return None;
+    }
+
+    Some((ndc.truncate() + Vec2::ONE) / 2.0 * window_size)
+}
+
+/// Selectable entities whose bounding sphere overlaps the window rectangle from `min` to `max`
+fn screen_space_entities(
+    camera: &Camera,
+    camera_transform: &GlobalTransform,
+    window_size: Vec2,
+    min: Vec2,
+    max: Vec2,
+    selectables: &Query<(Entity, &GlobalTransform, &units::Selectable)>,
+) -> Vec<Entity> {
+    let right = camera_transform.rotation * Vec3::X;
+
+    selectables
+        .iter()
+        .filter(|(_, transform, selectable)| {
+            let center = transform.translation;
+            let center_screen = world_to_screen(camera, camera_transform, window_size, center);
+            let edge_screen = world_to_screen(
+                camera,
+                camera_transform,
+                window_size,
+                center + right * selectable.radius,
+            );
+
+            match (center_screen, edge_screen) {
+                (Some(center), Some(edge)) => {
+                    circle_overlaps_rect(center, center.distance(edge), min, max)
+                }
+                _ => false,
+            }
+        })
+        .map(|(entity, _, _)| entity)
+        .collect()
+}
+
+/// Whether a circle overlaps the rectangle from `min` to `max`, which is the case if the nearest
+/// point of the rectangle is within the circle's radius
+fn circle_overlaps_rect(center: Vec2, radius: f32, min: Vec2, max: Vec2) -> bool {
+    let nearest = center.max(min).min(max);
+    nearest.distance(center) <= radius
+}
+
+/// The world space volume under a window rectangle, between the near and far planes. Returns
+/// `None` if the rectangle is too small to form a convex volume.
+fn selection_frustum(
+    camera: &Camera,
+    camera_transform: &GlobalTransform,
+    window_size: Vec2,
+    min: Vec2,
+    max: Vec2,
+) -> Option<ConvexPolyhedron> {
+    let corners = [
+        vec2(min.x, min.y),
+        vec2(max.x, min.y),
+        vec2(max.x, max.y),
+        vec2(min.x, max.y),
+    ];
+
+    let mut points: Vec<Point<Real>> = Vec::with_capacity(8);
+
+    // unprojecting the corners works for both perspective and orthographic cameras
+    for corner in corners.iter() {
+        let ray = crate::player::camera::MouseRay::from_screen(
+            camera,
+            camera_transform,
+            window_size,
+            *corner,
+        );
+
+        points.push(ray.near.into());
+        points.push(ray.far.into());
+    }
+
+    ConvexPolyhedron::from_convex_mesh(points, FRUSTUM_INDICES)
+}
+
+/// Entities whose colliders intersect the frustum
+fn collider_entities(
+    query_pipeline: &QueryPipeline,
+    collider_query: &QueryPipelineColliderComponentsQuery,
+    frustum: &ConvexPolyhedron,
+) -> Vec<Entity> {
+    let collider_set = QueryPipelineColliderComponentsSet(collider_query);
+    let groups = InteractionGroups::all();
+    let mut entities = Vec::new();
+
+    query_pipeline.intersections_with_shape(
+        &collider_set,
+        &[0.0, 0.0, 0.0].into(), // we constructed the frustum with worldspace coordinates
+        frustum,
+        groups,
+        None,
+        |handle| {
+            let entity = handle.entity();
+            log::debug!("The entity {:?} intersects the selection frustum", entity);
+
+            entities.push(entity);
+            true
+        },
+    );
+
+    entities
 }
 
 // These indices are used to form a convex polyhedron from
@@ -207,3 +577,200 @@ fn debug_draw_frustum(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::{InputEvent, RecordedFrame};
+    use bevy::{
+        ecs::schedule::{Stage, SystemStage},
+        render::camera::{CameraProjection, PerspectiveProjection},
+        window::{WindowDescriptor, WindowId},
+    };
+
+    const WINDOW_WIDTH: f32 = 800.0;
+    const WINDOW_HEIGHT: f32 = 600.0;
+
+    /// A perspective camera looking down -Z at the origin from 20 units away
+    fn player_camera() -> (Camera, GlobalTransform) {
+        let mut projection = PerspectiveProjection::default();
+        projection.update(WINDOW_WIDTH, WINDOW_HEIGHT);
+
+        let camera = Camera {
+            projection_matrix: projection.get_projection_matrix(),
+            ..Default::default()
+        };
+        let transform = GlobalTransform::from_xyz(0.0, 0.0, 20.0).looking_at(Vec3::ZERO, Vec3::Y);
+
+        (camera, transform)
+    }
+
+    fn spawn_unit(world: &mut World, position: Vec3) -> Entity {
+        world
+            .spawn()
+            .insert(GlobalTransform::from_translation(position))
+            .insert(units::UnitClass::Ship)
+            .insert(units::Faction::Player)
+            .insert(units::Selectable { radius: 1.0 })
+            .id()
+    }
+
+    /// A world with both the player camera and a UI camera, as the minimap adds one
+    fn selection_world() -> (World, SystemStage) {
+        let mut world = World::default();
+
+        let mut windows = Windows::default();
+        windows.add(Window::new(
+            WindowId::primary(),
+            &WindowDescriptor {
+                width: WINDOW_WIDTH,
+                height: WINDOW_HEIGHT,
+                ..Default::default()
+            },
+            WINDOW_WIDTH as u32,
+            WINDOW_HEIGHT as u32,
+            1.0,
+            None,
+        ));
+        world.insert_resource(windows);
+
+        let mut inputs = MappedInput::default();
+        inputs.bind([MouseButton::Left], Select::Replace);
+        world.insert_resource(inputs);
+
+        world.insert_resource(QueryPipeline::default());
+        world.insert_resource(DragCoords::default());
+        world.insert_resource(SelectionFilter::default());
+        world.insert_resource(SelectionBackend::default());
+
+        world
+            .spawn()
+            .insert(Camera::default())
+            .insert(GlobalTransform::identity());
+
+        let (camera, transform) = player_camera();
+        world
+            .spawn()
+            .insert(camera)
+            .insert(transform)
+            .insert(CameraController::default());
+
+        (world, SystemStage::single(drag_selection.system()))
+    }
+
+    fn frame(world: &mut World, stage: &mut SystemStage, time: f64, events: Vec<InputEvent>) {
+        world
+            .get_resource_mut::<MappedInput>()
+            .unwrap()
+            .apply_frame(&RecordedFrame { time, events });
+
+        stage.run(world);
+    }
+
+    fn drag(world: &mut World, stage: &mut SystemStage, start: Vec2, end: Vec2) {
+        let button = Switch::from(MouseButton::Left);
+
+        frame(
+            world,
+            stage,
+            0.0,
+            vec![InputEvent::Cursor(start), InputEvent::Press(button)],
+        );
+        frame(world, stage, 0.1, vec![InputEvent::Cursor(end)]);
+        frame(world, stage, 0.2, vec![InputEvent::Release(button)]);
+    }
+
+    #[test]
+    fn drag_with_ui_camera_present() {
+        let (mut world, mut stage) = selection_world();
+        let inside = spawn_unit(&mut world, Vec3::ZERO);
+        let outside = spawn_unit(&mut world, Vec3::new(6.0, 0.0, 0.0));
+
+        // the origin is in the middle of the window
+        drag(
+            &mut world,
+            &mut stage,
+            Vec2::new(350.0, 250.0),
+            Vec2::new(450.0, 350.0),
+        );
+
+        assert!(world.get::<units::Selected>(inside).is_some());
+        assert!(world.get::<units::Selected>(outside).is_none());
+    }
+
+    #[test]
+    fn world_to_screen_projects_to_window() {
+        let (camera, transform) = player_camera();
+        let size = Vec2::new(WINDOW_WIDTH, WINDOW_HEIGHT);
+
+        let centre = world_to_screen(&camera, &transform, size, Vec3::ZERO).unwrap();
+        assert!(centre.distance(size / 2.0) < 1e-3, "{:?}", centre);
+
+        // window coordinates start at the bottom left
+        let up_right = world_to_screen(&camera, &transform, size, Vec3::new(1.0, 1.0, 0.0));
+        let up_right = up_right.unwrap();
+        assert!(up_right.x > centre.x && up_right.y > centre.y);
+
+        // behind the camera
+        assert_eq!(
+            world_to_screen(&camera, &transform, size, Vec3::new(0.0, 0.0, 30.0)),
+            None
+        );
+    }
+
+    #[test]
+    fn circle_rect_overlap() {
+        let min = Vec2::new(100.0, 100.0);
+        let max = Vec2::new(200.0, 150.0);
+
+        let overlaps = |x, y, radius| circle_overlaps_rect(Vec2::new(x, y), radius, min, max);
+
+        assert!(overlaps(150.0, 120.0, 1.0));
+        // outside the rectangle, but reaching into it
+        assert!(overlaps(210.0, 120.0, 15.0));
+        assert!(!overlaps(210.0, 120.0, 5.0));
+        // near a corner, the distance is to the corner rather than to either edge
+        assert!(!overlaps(208.0, 158.0, 10.0));
+        assert!(overlaps(205.0, 155.0, 10.0));
+    }
+
+    #[test]
+    fn drag_selects_units_partly_inside() {
+        let (mut world, mut stage) = selection_world();
+        // the centre projects to the right of the box, but its radius reaches into it
+        let unit = spawn_unit(&mut world, Vec3::new(2.2, 0.0, 0.0));
+
+        drag(
+            &mut world,
+            &mut stage,
+            Vec2::new(350.0, 250.0),
+            Vec2::new(450.0, 350.0),
+        );
+
+        assert!(world.get::<units::Selected>(unit).is_some());
+    }
+
+    #[test]
+    fn degenerate_boxes() {
+        let (camera, transform) = player_camera();
+        let size = Vec2::new(WINDOW_WIDTH, WINDOW_HEIGHT);
+        let point = Vec2::new(400.0, 300.0);
+
+        assert!(selection_frustum(&camera, &transform, size, point, point).is_none());
+
+        // a box thinner than the minimum drag size is a click on empty space, which clears the
+        // selection rather than selecting what is under the line
+        let (mut world, mut stage) = selection_world();
+        let unit = spawn_unit(&mut world, Vec3::ZERO);
+        world.entity_mut(unit).insert(units::Selected);
+
+        drag(
+            &mut world,
+            &mut stage,
+            Vec2::new(300.0, 300.0),
+            Vec2::new(500.0, 302.0),
+        );
+
+        assert!(world.get::<units::Selected>(unit).is_none());
+    }
+}
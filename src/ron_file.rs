@@ -0,0 +1,59 @@
+//! Loading and saving of the RON files which keymaps, input recordings and camera bookmarks are
+//! kept in.
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    error::Error,
+    fmt, io,
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug)]
+pub enum RonFileError {
+    Io(PathBuf, io::Error),
+    Parse(PathBuf, ron::Error),
+    Serialize(PathBuf, ron::Error),
+}
+
+impl RonFileError {
+    /// Whether the file doesn't exist, which usually just means nothing has been saved yet
+    pub fn is_not_found(&self) -> bool {
+        matches!(self, RonFileError::Io(_, e) if e.kind() == io::ErrorKind::NotFound)
+    }
+}
+
+impl fmt::Display for RonFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RonFileError::Io(path, e) => write!(f, "failed to access {}: {}", path.display(), e),
+            RonFileError::Parse(path, e) => write!(f, "failed to parse {}: {}", path.display(), e),
+            RonFileError::Serialize(path, e) => {
+                write!(f, "failed to serialize {}: {}", path.display(), e)
+            }
+        }
+    }
+}
+
+impl Error for RonFileError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            RonFileError::Io(_, e) => Some(e),
+            RonFileError::Parse(_, e) | RonFileError::Serialize(_, e) => Some(e),
+        }
+    }
+}
+
+pub fn load<T: DeserializeOwned>(path: impl AsRef<Path>) -> Result<T, RonFileError> {
+    let path = path.as_ref();
+    let contents =
+        std::fs::read_to_string(path).map_err(|e| RonFileError::Io(path.to_owned(), e))?;
+
+    ron::from_str(&contents).map_err(|e| RonFileError::Parse(path.to_owned(), e))
+}
+
+pub fn save<T: Serialize>(value: &T, path: impl AsRef<Path>) -> Result<(), RonFileError> {
+    let path = path.as_ref();
+    let contents = ron::ser::to_string_pretty(value, ron::ser::PrettyConfig::new())
+        .map_err(|e| RonFileError::Serialize(path.to_owned(), e))?;
+
+    std::fs::write(path, contents).map_err(|e| RonFileError::Io(path.to_owned(), e))
+}
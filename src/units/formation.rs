@@ -0,0 +1,317 @@
+use bevy::prelude::*;
+
+/// How a group of units arranges itself around the target of a group move
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Formation {
+    /// Keep the group's current layout, moved so that its centre lands on the target
+    Preserve,
+    /// A single rank across the direction of travel
+    Line,
+    /// A V pointing in the direction of travel, led by one unit at the target
+    Wedge,
+    /// Spread over the surface of a sphere around the target
+    Sphere,
+}
+
+impl Default for Formation {
+    fn default() -> Self {
+        Formation::Preserve
+    }
+}
+
+impl Formation {
+    /// The formation after this one, wrapping around
+    pub fn next(self) -> Formation {
+        match self {
+            Formation::Preserve => Formation::Line,
+            Formation::Line => Formation::Wedge,
+            Formation::Wedge => Formation::Sphere,
+            Formation::Sphere => Formation::Preserve,
+        }
+    }
+
+    /// Slot positions for `count` units around the origin, in a frame where -Z is the direction
+    /// of travel and +X is to the right
+    fn local_slots(self, count: usize, spacing: f32) -> Vec<Vec3> {
+        match self {
+            Formation::Preserve => vec![Vec3::ZERO; count],
+            Formation::Line => (0..count)
+                .map(|i| Vec3::X * (i as f32 - (count as f32 - 1.0) / 2.0) * spacing)
+                .collect(),
+            Formation::Wedge => (0..count)
+                .map(|i| {
+                    // the leader takes the tip, then units alternate between the two arms
+                    let rank = ((i + 1) / 2) as f32;
+                    let side = if i % 2 == 0 { 1.0 } else { -1.0 };
+                    Vec3::new(side * rank * spacing, 0.0, rank * spacing)
+                })
+                .collect(),
+            Formation::Sphere => {
+                if count < 2 {
+                    return vec![Vec3::ZERO; count];
+                }
+
+                // spread the units evenly along a fibonacci spiral, and size the sphere so each
+                // unit gets about spacing² of its surface
+                let golden_angle = std::f32::consts::PI * (3.0 - 5f32.sqrt());
+                let points: Vec<Vec3> = (0..count)
+                    .map(|i| {
+                        let y = 1.0 - 2.0 * (i as f32 + 0.5) / count as f32;
+                        let ring = (1.0 - y * y).sqrt();
+                        let angle = golden_angle * i as f32;
+                        Vec3::new(ring * angle.cos(), y, ring * angle.sin())
+                    })
+                    .collect();
+
+                // the spiral isn't perfectly even, so grow the sphere until no two units are
+                // closer than spacing
+                let closest = points
+                    .iter()
+                    .enumerate()
+                    .flat_map(|(i, a)| points[i + 1..].iter().map(move |b| a.distance(*b)))
+                    .fold(f32::INFINITY, f32::min);
+                let radius = (spacing * (count as f32 / (4.0 * std::f32::consts::PI)).sqrt())
+                    .max(spacing / closest);
+
+                points.into_iter().map(|point| point * radius).collect()
+            }
+        }
+    }
+}
+
+/// Destinations of a group of units moving to `target` in `formation`, in the same order as
+/// `positions`. The formation faces the horizontal direction from the group's centre to the
+/// target, and units are assigned to slots so that their total travel distance is minimised.
+pub fn formation_slots(
+    formation: Formation,
+    spacing: f32,
+    positions: &[Vec3],
+    target: Vec3,
+) -> Vec<Vec3> {
+    if positions.is_empty() {
+        return Vec::new();
+    }
+
+    let centre = positions.iter().fold(Vec3::ZERO, |sum, &p| sum + p) / positions.len() as f32;
+
+    if formation == Formation::Preserve {
+        return positions.iter().map(|&p| target + p - centre).collect();
+    }
+
+    let travel = Vec3::new(target.x - centre.x, 0.0, target.z - centre.z);
+    let forward = if travel.length() > f32::EPSILON {
+        travel.normalize()
+    } else {
+        -Vec3::Z
+    };
+    let right = forward.cross(Vec3::Y);
+    let local_to_world = Mat3::from_cols(right, Vec3::Y, -forward);
+
+    let slots: Vec<Vec3> = formation
+        .local_slots(positions.len(), spacing)
+        .into_iter()
+        .map(|slot| target + local_to_world * slot)
+        .collect();
+
+    let cost: Vec<Vec<f32>> = positions
+        .iter()
+        .map(|&p| {
+            slots
+                .iter()
+                // keeps the costs finite, as the assignment can't handle NaN or infinity
+                .map(|&slot| p.distance(slot).min(1.0e6))
+                .collect()
+        })
+        .collect();
+
+    assign_slots(&cost)
+        .into_iter()
+        .map(|slot| slots[slot])
+        .collect()
+}
+
+/// Solve the assignment problem for a square cost matrix with the Hungarian algorithm, returning
+/// the column assigned to each row such that the total cost is minimal.
+pub fn assign_slots(cost: &[Vec<f32>]) -> Vec<usize> {
+    let n = cost.len();
+
+    // potentials and matching are 1-indexed, with index 0 as a sentinel
+    let mut u = vec![0.0f32; n + 1];
+    let mut v = vec![0.0f32; n + 1];
+    // the row matched to each column
+    let mut matched = vec![0usize; n + 1];
+    let mut way = vec![0usize; n + 1];
+
+    for row in 1..=n {
+        matched[0] = row;
+        let mut column = 0;
+        let mut min_slack = vec![f32::INFINITY; n + 1];
+        let mut used = vec![false; n + 1];
+
+        // grow an alternating path from the new row until it reaches a free column
+        loop {
+            used[column] = true;
+            let current_row = matched[column];
+            let mut delta = f32::INFINITY;
+            let mut next = 0;
+
+            for j in 1..=n {
+                if used[j] {
+                    continue;
+                }
+
+                let slack = cost[current_row - 1][j - 1] - u[current_row] - v[j];
+                if slack < min_slack[j] {
+                    min_slack[j] = slack;
+                    way[j] = column;
+                }
+                if min_slack[j] < delta {
+                    delta = min_slack[j];
+                    next = j;
+                }
+            }
+
+            for j in 0..=n {
+                if used[j] {
+                    u[matched[j]] += delta;
+                    v[j] -= delta;
+                } else {
+                    min_slack[j] -= delta;
+                }
+            }
+
+            column = next;
+            if matched[column] == 0 {
+                break;
+            }
+        }
+
+        // flip the matching along the path
+        while column != 0 {
+            let previous = way[column];
+            matched[column] = matched[previous];
+            column = previous;
+        }
+    }
+
+    let mut assignment = vec![0; n];
+    for j in 1..=n {
+        assignment[matched[j] - 1] = j - 1;
+    }
+    assignment
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Every permutation of `0..n`
+    fn permutations(n: usize) -> Vec<Vec<usize>> {
+        fn generate(start: usize, items: &mut Vec<usize>, out: &mut Vec<Vec<usize>>) {
+            if start == items.len() {
+                out.push(items.clone());
+                return;
+            }
+
+            for i in start..items.len() {
+                items.swap(start, i);
+                generate(start + 1, items, out);
+                items.swap(start, i);
+            }
+        }
+
+        let mut out = Vec::new();
+        generate(0, &mut (0..n).collect(), &mut out);
+        out
+    }
+
+    fn total(cost: &[Vec<f32>], assignment: &[usize]) -> f32 {
+        assignment
+            .iter()
+            .enumerate()
+            .map(|(row, &column)| cost[row][column])
+            .sum()
+    }
+
+    #[test]
+    fn assign_slots_matches_brute_force() {
+        // a small linear congruential generator keeps the costs reproducible
+        let mut seed = 0x2545_f491u32;
+        let mut random = move || {
+            seed = seed.wrapping_mul(1_664_525).wrapping_add(1_013_904_223);
+            (seed >> 8) as f32 / (1 << 24) as f32
+        };
+
+        for n in 0..=7 {
+            let all = permutations(n);
+
+            // the sphere is the only formation whose spacing isn't exact by construction
+            let slots = Formation::Sphere.local_slots(n, 3.0);
+            for (i, a) in slots.iter().enumerate() {
+                for b in &slots[i + 1..] {
+                    assert!(
+                        a.distance(*b) >= 3.0 - 1e-4,
+                        "n = {}: slots {:?} and {:?} are too close",
+                        n,
+                        a,
+                        b
+                    );
+                }
+            }
+
+            for _ in 0..20 {
+                let cost: Vec<Vec<f32>> = (0..n)
+                    .map(|_| (0..n).map(|_| random() * 100.0).collect())
+                    .collect();
+
+                let assignment = assign_slots(&cost);
+
+                let mut columns = assignment.clone();
+                columns.sort_unstable();
+                assert_eq!(columns, (0..n).collect::<Vec<_>>(), "not a permutation");
+
+                let best = all
+                    .iter()
+                    .map(|permutation| total(&cost, permutation))
+                    .fold(f32::INFINITY, f32::min);
+                let found = total(&cost, &assignment);
+
+                assert!(
+                    found - best <= 1e-3,
+                    "n = {}: found {} but the best is {}",
+                    n,
+                    found,
+                    best
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn assign_slots_with_ties() {
+        let cost = vec![vec![1.0; 4]; 4];
+        let mut assignment = assign_slots(&cost);
+        assignment.sort_unstable();
+        assert_eq!(assignment, vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn formation_slots_keep_order_of_positions() {
+        // units already standing in a line are sent straight ahead rather than crossing over
+        let positions: Vec<Vec3> = (0..5)
+            .map(|i| Vec3::new(i as f32 * 3.0 - 6.0, 0.0, 0.0))
+            .collect();
+        let target = Vec3::new(0.0, 0.0, -30.0);
+
+        let slots = formation_slots(Formation::Line, 3.0, &positions, target);
+
+        for (position, slot) in positions.iter().zip(slots.iter()) {
+            assert!(
+                (position.x - slot.x).abs() < 1e-4,
+                "{:?} -> {:?}",
+                position,
+                slot
+            );
+        }
+    }
+}
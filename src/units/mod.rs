@@ -6,12 +6,106 @@ pub mod ship;
 
 mod movement;
 pub use movement::MoveTarget;
+mod formation;
+pub use formation::{assign_slots, formation_slots, Formation};
+mod orders;
+pub use orders::{Order, OrderQueue, ARRIVAL_RADIUS, FOLLOW_DISTANCE, GUARD_RADIUS};
 pub struct Selected;
+
+/// Marks a unit as selectable by drag selection, with the radius of a sphere bounding it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Selectable {
+    pub radius: f32,
+}
+
+impl Default for Selectable {
+    fn default() -> Self {
+        Selectable { radius: 1.0 }
+    }
+}
+
+/// The side a unit belongs to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Faction {
+    Player,
+    Enemy,
+    Neutral,
+}
+
+impl Default for Faction {
+    fn default() -> Self {
+        Faction::Player
+    }
+}
+
+impl Faction {
+    /// Colour used for the faction on overviews such as the minimap
+    pub fn color(self) -> Color {
+        match self {
+            Faction::Player => Color::rgb(0.2, 0.8, 0.3),
+            Faction::Enemy => Color::rgb(0.9, 0.2, 0.2),
+            Faction::Neutral => Color::rgb(0.8, 0.8, 0.8),
+        }
+    }
+}
+
+/// What kind of unit an entity is, used to pick out similar units and to filter selections
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UnitClass {
+    Ship,
+    Station,
+}
+
+impl Default for UnitClass {
+    fn default() -> Self {
+        UnitClass::Ship
+    }
+}
+
+impl UnitClass {
+    /// Whether units of this class can be ordered to move
+    pub fn is_mobile(self) -> bool {
+        match self {
+            UnitClass::Ship => true,
+            UnitClass::Station => false,
+        }
+    }
+}
 pub struct UnitsPlugin;
 
 impl Plugin for UnitsPlugin {
     fn build(&self, app: &mut AppBuilder) {
         app
+            .add_system(
+                orders::move_orders
+                    .system()
+                    .after(SystemLabels::Input),
+            )
+            .add_system(
+                orders::stop_orders
+                    .system()
+                    .after(SystemLabels::Input),
+            )
+            .add_system(
+                orders::hold_position_orders
+                    .system()
+                    .after(SystemLabels::Input),
+            )
+            .add_system(
+                orders::patrol_orders
+                    .system()
+                    .after(SystemLabels::Input),
+            )
+            .add_system(
+                orders::follow_orders
+                    .system()
+                    .after(SystemLabels::Input),
+            )
+            .add_system(
+                orders::guard_orders
+                    .system()
+                    .after(SystemLabels::Input),
+            )
             .add_system(
                 movement::movement_system
                     .system()
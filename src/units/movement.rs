@@ -20,8 +20,13 @@ pub fn movement_system(
         let disp: Vec3 = movetarget.0 - transform.translation;
         let acc = 10.0;
 
-        let direction = disp.normalize();
         let distance = disp.length();
+        // units ordered to stop are steered towards where they already are
+        let direction = if distance > f32::EPSILON {
+            disp / distance
+        } else {
+            Vec3::ZERO
+        };
         let optimal_speed = (2.0 * acc * distance).sqrt();
         let optimal_velocity = (direction * optimal_speed);
 
@@ -0,0 +1,222 @@
+use std::collections::VecDeque;
+
+use bevy::prelude::*;
+
+use super::MoveTarget;
+
+/// Distance from a waypoint at which a unit counts as having arrived
+pub const ARRIVAL_RADIUS: f32 = 1.0;
+/// Distance a following unit keeps from the unit it follows
+pub const FOLLOW_DISTANCE: f32 = 3.0;
+/// Distance from the guarded unit within which a guarding unit stays
+pub const GUARD_RADIUS: f32 = 6.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Order {
+    Move(Vec3),
+    /// Come to a halt where the unit is
+    Stop,
+    /// Stay at a position until given another order
+    HoldPosition(Vec3),
+    /// Move to a waypoint, then queue it again, so that consecutive patrol orders loop
+    Patrol(Vec3),
+    /// Trail behind another unit for as long as it exists
+    Follow(Entity),
+    /// Stay close to another unit for as long as it exists
+    Guard(Entity),
+}
+
+impl Order {
+    /// The fixed position the order heads for, if it has one
+    pub fn waypoint(&self) -> Option<Vec3> {
+        match *self {
+            Order::Move(target) | Order::HoldPosition(target) | Order::Patrol(target) => {
+                Some(target)
+            }
+            Order::Stop | Order::Follow(_) | Order::Guard(_) => None,
+        }
+    }
+
+    /// The unit the order tracks, if any
+    pub fn entity(&self) -> Option<Entity> {
+        match *self {
+            Order::Follow(entity) | Order::Guard(entity) => Some(entity),
+            _ => None,
+        }
+    }
+}
+
+/// Orders a unit carries out one after another, starting from the front
+#[derive(Debug, Clone, Default)]
+pub struct OrderQueue {
+    orders: VecDeque<Order>,
+}
+
+impl OrderQueue {
+    /// Drop every queued order and carry out `order` instead
+    pub fn replace(&mut self, order: Order) {
+        self.orders.clear();
+        self.orders.push_back(order);
+    }
+
+    /// Carry out `order` once the queued orders are completed
+    pub fn push(&mut self, order: Order) {
+        self.orders.push_back(order);
+    }
+
+    /// The order currently being carried out
+    pub fn current(&self) -> Option<&Order> {
+        self.orders.front()
+    }
+
+    /// Finish the current order and move on to the next one
+    pub fn complete(&mut self) -> Option<Order> {
+        self.orders.pop_front()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Order> {
+        self.orders.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.orders.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.orders.clear();
+    }
+}
+
+/// Point the unit's steering at `target`, unless it already is
+fn steer(commands: &mut Commands, entity: Entity, current: Option<&MoveTarget>, target: Vec3) {
+    if current.map(|t| t.0) != Some(target) {
+        commands.entity(entity).insert(MoveTarget(target));
+    }
+}
+
+type OrderQuery<'w> = Query<
+    'w,
+    (
+        Entity,
+        &'static GlobalTransform,
+        &'static mut OrderQueue,
+        Option<&'static MoveTarget>,
+    ),
+>;
+
+// Each kind of order is carried out by its own system below, which only acts on units whose
+// current order is of its kind. Once a unit's queue runs out it keeps the steering target of its
+// last order, and so holds that position.
+
+pub fn move_orders(mut commands: Commands, mut query: OrderQuery) {
+    for (entity, transform, mut queue, move_target) in query.iter_mut() {
+        if let Some(&Order::Move(target)) = queue.current() {
+            if transform.translation.distance(target) <= ARRIVAL_RADIUS {
+                queue.complete();
+            } else {
+                steer(&mut commands, entity, move_target, target);
+            }
+        }
+    }
+}
+
+pub fn stop_orders(mut commands: Commands, mut query: OrderQuery) {
+    for (entity, transform, mut queue, move_target) in query.iter_mut() {
+        if let Some(Order::Stop) = queue.current() {
+            steer(&mut commands, entity, move_target, transform.translation);
+            queue.complete();
+        }
+    }
+}
+
+pub fn hold_position_orders(mut commands: Commands, mut query: OrderQuery) {
+    for (entity, _, queue, move_target) in query.iter_mut() {
+        if let Some(&Order::HoldPosition(position)) = queue.current() {
+            steer(&mut commands, entity, move_target, position);
+        }
+    }
+}
+
+pub fn patrol_orders(mut commands: Commands, mut query: OrderQuery) {
+    for (entity, transform, mut queue, move_target) in query.iter_mut() {
+        if let Some(&Order::Patrol(target)) = queue.current() {
+            if transform.translation.distance(target) <= ARRIVAL_RADIUS {
+                queue.complete();
+                queue.push(Order::Patrol(target));
+            } else {
+                steer(&mut commands, entity, move_target, target);
+            }
+        }
+    }
+}
+
+pub fn follow_orders(
+    mut commands: Commands,
+    mut query: OrderQuery,
+    leaders: Query<&GlobalTransform>,
+) {
+    for (entity, transform, mut queue, move_target) in query.iter_mut() {
+        if let Some(&Order::Follow(leader)) = queue.current() {
+            let leader = match leaders.get(leader) {
+                Ok(leader) => leader.translation,
+                Err(_) => {
+                    queue.complete();
+                    continue;
+                }
+            };
+
+            // keep to the point at the follow distance on this unit's side of the leader
+            let offset = transform.translation - leader;
+            let direction = if offset.length() > f32::EPSILON {
+                offset.normalize()
+            } else {
+                Vec3::Z
+            };
+
+            steer(
+                &mut commands,
+                entity,
+                move_target,
+                leader + direction * FOLLOW_DISTANCE,
+            );
+        }
+    }
+}
+
+pub fn guard_orders(
+    mut commands: Commands,
+    mut query: OrderQuery,
+    guarded: Query<&GlobalTransform>,
+) {
+    for (entity, transform, mut queue, move_target) in query.iter_mut() {
+        if let Some(&Order::Guard(target)) = queue.current() {
+            let target = match guarded.get(target) {
+                Ok(target) => target.translation,
+                Err(_) => {
+                    queue.complete();
+                    continue;
+                }
+            };
+
+            // only pick a new position once the current one is out of range, so that guards
+            // don't chase every small movement of the guarded unit
+            if move_target.map_or(false, |t| t.0.distance(target) <= GUARD_RADIUS) {
+                continue;
+            }
+
+            let offset = transform.translation - target;
+            let direction = if offset.length() > f32::EPSILON {
+                offset.normalize()
+            } else {
+                Vec3::Z
+            };
+
+            steer(
+                &mut commands,
+                entity,
+                move_target,
+                target + direction * GUARD_RADIUS / 2.0,
+            );
+        }
+    }
+}
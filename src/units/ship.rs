@@ -18,6 +18,10 @@ struct ShipBundle {
     rigid_body: physics::RigidBodyBundle,
     #[bundle]
     pickable: PickableBundle,
+    faction: super::Faction,
+    class: super::UnitClass,
+    selectable: super::Selectable,
+    orders: super::OrderQueue,
 }
 
 pub fn spawn_station(
@@ -53,7 +57,10 @@ pub fn spawn_station(
         .insert(color.add(ColorMaterial {
             color: Color::RED,
             ..Default::default()
-        }));
+        }))
+        .insert(super::Faction::Neutral)
+        .insert(super::UnitClass::Station)
+        .insert(super::Selectable { radius: 5.0 });
 }
 
 pub fn spawn_ship(
@@ -81,6 +88,10 @@ pub fn spawn_ship(
                 shape: physics::ColliderShape::cuboid(0.5, 0.5, 0.5),
                 ..physics::ColliderBundle::default()
             },
+            selectable: super::Selectable {
+                // bounds the collider cuboid
+                radius: 0.87,
+            },
             pbr_bundle: PbrBundle {
                 mesh: cube_handle,
                 material: material_handle,